@@ -0,0 +1,5 @@
+DROP TABLE images;
+DROP TABLE votes;
+DROP TABLE submissions;
+
+DROP TYPE submission_decision;
@@ -0,0 +1,7 @@
+ALTER TABLE submissions
+    ADD COLUMN pending_approval BOOLEAN NOT NULL DEFAULT TRUE;
+
+UPDATE submissions
+SET pending_approval = submission_id IN (SELECT submission_id FROM pending_approvals);
+
+DROP TABLE pending_approvals;
@@ -0,0 +1,4 @@
+-- Fails while there are submissions without a decision, which can't be
+-- represented before this migration.
+ALTER TABLE submissions
+    ALTER COLUMN decision SET NOT NULL;
@@ -0,0 +1,2 @@
+ALTER TABLE pending_approvals
+    DROP COLUMN date;
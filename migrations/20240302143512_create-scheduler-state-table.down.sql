@@ -0,0 +1,3 @@
+DROP TABLE scheduler_state;
+
+DROP TYPE scheduler_status;
@@ -0,0 +1,13 @@
+CREATE TYPE scheduler_status AS ENUM ('running', 'paused');
+
+CREATE TABLE IF NOT EXISTS scheduler_state (
+    singleton BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (singleton),
+
+    status scheduler_status NOT NULL DEFAULT 'running',
+    reason TEXT,
+    changed_by BIGINT,
+
+    change_date TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO scheduler_state DEFAULT VALUES;
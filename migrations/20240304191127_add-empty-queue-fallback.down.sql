@@ -0,0 +1,5 @@
+DROP TABLE reposts;
+
+ALTER TABLE scheduler_state
+    DROP COLUMN empty_queue_notice_date,
+    DROP COLUMN low_queue_warning_date;
@@ -0,0 +1,12 @@
+ALTER TABLE scheduler_state
+    ADD COLUMN empty_queue_notice_date TIMESTAMP,
+    ADD COLUMN low_queue_warning_date TIMESTAMP;
+
+CREATE TABLE IF NOT EXISTS reposts (
+    repost_id SERIAL PRIMARY KEY,
+    submission_id INTEGER NOT NULL,
+
+    repost_date TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    FOREIGN KEY (submission_id) REFERENCES submissions(submission_id)
+);
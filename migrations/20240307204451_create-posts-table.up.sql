@@ -0,0 +1,20 @@
+CREATE TYPE post_state AS ENUM ('pending', 'uploaded', 'published', 'failed');
+
+CREATE TABLE IF NOT EXISTS posts (
+    post_id SERIAL PRIMARY KEY,
+    submission_id INTEGER NOT NULL,
+
+    idempotency_key UUID NOT NULL UNIQUE,
+    repost BOOLEAN NOT NULL DEFAULT FALSE,
+    state post_state NOT NULL DEFAULT 'pending',
+
+    media_ids TEXT[] NOT NULL DEFAULT '{}',
+    status_id TEXT,
+    status_url TEXT,
+    error TEXT,
+
+    attempt_date TIMESTAMP NOT NULL DEFAULT NOW(),
+    publish_date TIMESTAMP,
+
+    FOREIGN KEY (submission_id) REFERENCES submissions(submission_id)
+);
@@ -0,0 +1,12 @@
+-- Values can't be removed from an enum, so the type is recreated without it.
+UPDATE posts SET state = 'failed' WHERE state = 'dead';
+
+ALTER TYPE post_state RENAME TO post_state_old;
+CREATE TYPE post_state AS ENUM ('pending', 'uploaded', 'published', 'failed');
+
+ALTER TABLE posts
+    ALTER COLUMN state DROP DEFAULT,
+    ALTER COLUMN state TYPE post_state USING state::TEXT::post_state,
+    ALTER COLUMN state SET DEFAULT 'pending';
+
+DROP TYPE post_state_old;
@@ -0,0 +1 @@
+ALTER TYPE post_state ADD VALUE 'dead';
@@ -0,0 +1,14 @@
+-- Only the first image of a submission fits back into it, the others are
+-- lost.
+ALTER TABLE submissions
+    ADD COLUMN sample_image_url TEXT;
+
+UPDATE submissions SET sample_image_url = images.image_path
+FROM images WHERE images.submission_id = submissions.submission_id AND images.position = 0;
+
+DELETE FROM images WHERE vote_id IS NULL;
+
+ALTER TABLE images
+    DROP CONSTRAINT images_submission_id_position_key,
+    DROP COLUMN position,
+    ALTER COLUMN vote_id SET NOT NULL;
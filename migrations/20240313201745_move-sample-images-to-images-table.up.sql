@@ -0,0 +1,13 @@
+ALTER TABLE images
+    ALTER COLUMN vote_id DROP NOT NULL,
+    ADD COLUMN position SMALLINT NOT NULL DEFAULT 0,
+    ADD CONSTRAINT images_submission_id_position_key UNIQUE (submission_id, position) DEFERRABLE INITIALLY DEFERRED;
+
+ALTER TABLE images
+    ALTER COLUMN position DROP DEFAULT;
+
+INSERT INTO images (submission_id, position, image_path)
+SELECT submission_id, 0, sample_image_url FROM submissions WHERE sample_image_url IS NOT NULL;
+
+ALTER TABLE submissions
+    DROP COLUMN sample_image_url;
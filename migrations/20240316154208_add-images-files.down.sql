@@ -0,0 +1,3 @@
+ALTER TABLE images
+    DROP COLUMN original_path,
+    DROP COLUMN processed_path;
@@ -0,0 +1,3 @@
+ALTER TABLE images
+    ADD COLUMN original_path TEXT,
+    ADD COLUMN processed_path TEXT;
@@ -0,0 +1,5 @@
+-- Posts to other targets would be mistaken for Mastodon ones without it.
+DELETE FROM posts WHERE target <> 'mastodon';
+
+ALTER TABLE posts
+    DROP COLUMN target;
@@ -0,0 +1,5 @@
+ALTER TABLE posts
+    ADD COLUMN target TEXT NOT NULL DEFAULT 'mastodon';
+
+ALTER TABLE posts
+    ALTER COLUMN target DROP DEFAULT;
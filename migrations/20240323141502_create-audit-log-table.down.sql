@@ -0,0 +1,5 @@
+DROP TABLE audit_log;
+
+DROP FUNCTION reject_audit_log_changes;
+
+DROP TYPE audit_action;
@@ -0,0 +1,32 @@
+CREATE TYPE audit_action AS ENUM ('approve', 'reject', 'edit_alt_text', 'edit_images', 'pause_posting', 'resume_posting');
+
+CREATE TABLE IF NOT EXISTS audit_log (
+    audit_id SERIAL PRIMARY KEY,
+    -- Actions taken from the command line don't have an actor.
+    actor_id BIGINT,
+    action audit_action NOT NULL,
+    submission_id INTEGER,
+
+    before_value TEXT,
+    after_value TEXT,
+
+    audit_date TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    FOREIGN KEY (submission_id) REFERENCES submissions(submission_id)
+);
+
+CREATE INDEX audit_log_submission_id_idx ON audit_log (submission_id);
+CREATE INDEX audit_log_actor_id_idx ON audit_log (actor_id);
+
+CREATE FUNCTION reject_audit_log_changes() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION reject_audit_log_changes();
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION reject_audit_log_changes();
@@ -0,0 +1,12 @@
+-- Votes of the team on pending submissions, a reviewer can change their vote
+-- until the submission is decided on.
+CREATE TABLE IF NOT EXISTS reviewer_votes (
+    submission_id INTEGER NOT NULL,
+    reviewer_id BIGINT NOT NULL,
+    approve BOOLEAN NOT NULL,
+
+    vote_date TIMESTAMP NOT NULL DEFAULT NOW(),
+
+    PRIMARY KEY (submission_id, reviewer_id),
+    FOREIGN KEY (submission_id) REFERENCES submissions(submission_id)
+);
@@ -0,0 +1,11 @@
+-- Values can't be dropped from an enum, so the type is made again without
+-- them. Their entries have to go first, which the table doesn't allow
+-- otherwise.
+ALTER TABLE audit_log DISABLE TRIGGER audit_log_append_only;
+DELETE FROM audit_log WHERE action IN ('expire', 'import_backlog');
+ALTER TABLE audit_log ENABLE TRIGGER audit_log_append_only;
+
+ALTER TYPE audit_action RENAME TO audit_action_old;
+CREATE TYPE audit_action AS ENUM ('approve', 'reject', 'edit_alt_text', 'edit_images', 'pause_posting', 'resume_posting');
+ALTER TABLE audit_log ALTER COLUMN action TYPE audit_action USING action::TEXT::audit_action;
+DROP TYPE audit_action_old;
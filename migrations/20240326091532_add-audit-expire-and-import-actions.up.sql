@@ -0,0 +1,2 @@
+ALTER TYPE audit_action ADD VALUE 'expire';
+ALTER TYPE audit_action ADD VALUE 'import_backlog';
@@ -0,0 +1,7 @@
+DROP INDEX posts_idempotency_key_attempt_date_idx;
+
+-- Retries get keys of their own again.
+UPDATE posts SET idempotency_key = gen_random_uuid()
+    WHERE post_id NOT IN (SELECT MIN(post_id) FROM posts GROUP BY idempotency_key);
+
+ALTER TABLE posts ADD CONSTRAINT posts_idempotency_key_key UNIQUE (idempotency_key);
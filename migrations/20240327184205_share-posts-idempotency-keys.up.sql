@@ -0,0 +1,5 @@
+-- Retries of a failed attempt are sent with its idempotency key, in case the
+-- target got it anyway, so attempts can share a key.
+ALTER TABLE posts DROP CONSTRAINT posts_idempotency_key_key;
+
+CREATE UNIQUE INDEX posts_idempotency_key_attempt_date_idx ON posts (idempotency_key, attempt_date);
@@ -0,0 +1,3 @@
+-- Attempts left for a later tick because of a rate limit, which aren't
+-- counted as failures.
+ALTER TYPE post_state ADD VALUE 'deferred';
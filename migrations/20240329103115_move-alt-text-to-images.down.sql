@@ -0,0 +1,9 @@
+-- Only the first image's description is kept.
+ALTER TABLE submissions
+    ADD COLUMN alt_text TEXT;
+
+UPDATE submissions SET alt_text = images.alt_text FROM images
+    WHERE images.submission_id = submissions.submission_id AND images.position = 0;
+
+ALTER TABLE images
+    DROP COLUMN alt_text;
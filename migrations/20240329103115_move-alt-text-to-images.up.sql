@@ -0,0 +1,11 @@
+-- Every image of a submission gets a description of its own. What was
+-- written for the whole submission goes to its first image, it likely only
+-- described that one.
+ALTER TABLE images
+    ADD COLUMN alt_text TEXT;
+
+UPDATE images SET alt_text = submissions.alt_text FROM submissions
+    WHERE images.submission_id = submissions.submission_id AND images.position = 0;
+
+ALTER TABLE submissions
+    DROP COLUMN alt_text;
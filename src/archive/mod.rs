@@ -0,0 +1,472 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::models::{
+    audit_log::{AuditEntry, AuditEntryHelpers},
+    images::{Image, ImageHelpers, UpdateImageFiles},
+    pending_approvals::{PendingApproval, PendingApprovalHelpers},
+    posts::{Post, PostHelpers},
+    reposts::{Repost, RepostHelpers},
+    reviewer_votes::{ReviewerVote, ReviewerVoteHelpers},
+    submissions::{Submission, SubmissionHelpers},
+    votes::{Vote, VoteHelpers},
+};
+
+/// Bumped whenever the records change in a way older versions can't import.
+pub const ARCHIVE_VERSION: u32 = 2;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const SUBMISSIONS_FILE: &str = "submissions.jsonl";
+const PENDING_APPROVALS_FILE: &str = "pending_approvals.jsonl";
+const REVIEWER_VOTES_FILE: &str = "reviewer_votes.jsonl";
+const VOTES_FILE: &str = "votes.jsonl";
+const IMAGES_FILE: &str = "images.jsonl";
+const POSTS_FILE: &str = "posts.jsonl";
+const REPOSTS_FILE: &str = "reposts.jsonl";
+const AUDIT_LOG_FILE: &str = "audit_log.jsonl";
+// Stored files keep their names, under the same directories as in the media
+// storage.
+const MEDIA_DIRECTORY: &str = "media";
+const ORIGINALS_DIRECTORY: &str = "originals";
+const PROCESSED_DIRECTORY: &str = "processed";
+
+/// Written last, so an archive without one was never finished.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub export_date: chrono::DateTime<chrono::Utc>,
+    /// Whether the stored files of images are in the archive.
+    pub media: bool,
+    pub records: RecordCounts,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RecordCounts {
+    pub submissions: usize,
+    pub pending_approvals: usize,
+    /// Archives from before reviewer votes were archived don't have any.
+    #[serde(default)]
+    pub reviewer_votes: usize,
+    pub votes: usize,
+    pub images: usize,
+    pub posts: usize,
+    pub reposts: usize,
+    /// Archives from before the audit log was archived don't have any.
+    #[serde(default)]
+    pub audit_entries: usize,
+}
+
+#[derive(Debug)]
+pub struct ImportReport {
+    pub archived: RecordCounts,
+    /// Records that weren't in the database yet.
+    pub inserted: RecordCounts,
+}
+
+impl fmt::Display for RecordCounts {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{submissions} submissions, {pending_approvals} pending approvals, {reviewer_votes} reviewer votes, {votes} votes, {images} images, {posts} posts, {reposts} reposts and {audit_entries} audit entries",
+            submissions = self.submissions,
+            pending_approvals = self.pending_approvals,
+            reviewer_votes = self.reviewer_votes,
+            votes = self.votes,
+            images = self.images,
+            posts = self.posts,
+            reposts = self.reposts,
+            audit_entries = self.audit_entries
+        )
+    }
+}
+
+/// Writes every submission with its approvals, votes, images, posts and
+/// audit entries to the `path` directory, as one JSON Lines file each.
+pub async fn export(
+    database: &PgPool,
+    path: &Path,
+    include_media: bool,
+) -> anyhow::Result<Manifest> {
+    if tokio::fs::try_exists(path.join(MANIFEST_FILE)).await? {
+        anyhow::bail!(
+            "`{path}` already has an archive in it",
+            path = path.display()
+        );
+    }
+    tokio::fs::create_dir_all(path).await?;
+
+    // Everything is read from one snapshot, so the files agree with each
+    // other even while the bot keeps running.
+    let mut transaction = database.begin().await?;
+    sqlx::query!("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ, READ ONLY")
+        .execute(&mut *transaction)
+        .await?;
+
+    let submissions = Submission::get_all_submissions(&mut *transaction).await?;
+    let pending_approvals = PendingApproval::get_all_pending_approvals(&mut *transaction).await?;
+    let reviewer_votes = ReviewerVote::get_all_reviewer_votes(&mut *transaction).await?;
+    let votes = Vote::get_all_votes(&mut *transaction).await?;
+    let images = Image::get_all_images(&mut *transaction).await?;
+    let posts = Post::get_all_posts(&mut *transaction).await?;
+    let reposts = Repost::get_all_reposts(&mut *transaction).await?;
+    let audit_entries = AuditEntry::get_all_audit_entries(&mut *transaction).await?;
+
+    transaction.commit().await?;
+
+    write_records(&path.join(SUBMISSIONS_FILE), &submissions).await?;
+    write_records(&path.join(PENDING_APPROVALS_FILE), &pending_approvals).await?;
+    write_records(&path.join(REVIEWER_VOTES_FILE), &reviewer_votes).await?;
+    write_records(&path.join(VOTES_FILE), &votes).await?;
+    write_records(&path.join(IMAGES_FILE), &images).await?;
+    write_records(&path.join(POSTS_FILE), &posts).await?;
+    write_records(&path.join(REPOSTS_FILE), &reposts).await?;
+    write_records(&path.join(AUDIT_LOG_FILE), &audit_entries).await?;
+
+    if include_media {
+        for image in &images {
+            for (directory, stored_path) in [
+                (ORIGINALS_DIRECTORY, &image.original_path),
+                (PROCESSED_DIRECTORY, &image.processed_path),
+            ] {
+                let Some(stored_path) = stored_path else {
+                    continue;
+                };
+                let stored_path = Path::new(stored_path);
+                let Some(file_name) = stored_path.file_name() else {
+                    continue;
+                };
+
+                let archived_path = path.join(MEDIA_DIRECTORY).join(directory).join(file_name);
+                if let Err(error) = copy_file(stored_path, &archived_path).await {
+                    warn!(
+                        "couldn't archive `{stored_path}` of image {image_id}, it will be downloaded again after importing: {error:#?}",
+                        stored_path = stored_path.display(),
+                        image_id = image.image_id
+                    );
+                }
+            }
+        }
+    }
+
+    let manifest = Manifest {
+        version: ARCHIVE_VERSION,
+        export_date: chrono::Utc::now(),
+        media: include_media,
+        records: RecordCounts {
+            submissions: submissions.len(),
+            pending_approvals: pending_approvals.len(),
+            reviewer_votes: reviewer_votes.len(),
+            votes: votes.len(),
+            images: images.len(),
+            posts: posts.len(),
+            reposts: reposts.len(),
+            audit_entries: audit_entries.len(),
+        },
+    };
+    tokio::fs::write(
+        path.join(MANIFEST_FILE),
+        serde_json::to_string_pretty(&manifest)?,
+    )
+    .await?;
+
+    Ok(manifest)
+}
+
+/// Adds the records of an archive made by [`export`] to the database, with
+/// new IDs. Records that are already there are skipped, so importing the
+/// same archive again changes nothing.
+pub async fn import(
+    database: &PgPool,
+    media_storage_path: &Path,
+    path: &Path,
+) -> anyhow::Result<ImportReport> {
+    let manifest = serde_json::from_slice::<Manifest>(
+        &tokio::fs::read(path.join(MANIFEST_FILE))
+            .await
+            .map_err(|error| {
+                anyhow::anyhow!(
+                    "`{path}` isn't a finished archive: {error}",
+                    path = path.display()
+                )
+            })?,
+    )?;
+    if manifest.version != ARCHIVE_VERSION {
+        anyhow::bail!(
+            "the archive is version {version}, but only version {ARCHIVE_VERSION} can be imported",
+            version = manifest.version
+        );
+    }
+
+    let submissions = read_records::<Submission>(&path.join(SUBMISSIONS_FILE)).await?;
+    let pending_approvals =
+        read_records::<PendingApproval>(&path.join(PENDING_APPROVALS_FILE)).await?;
+    let reviewer_votes =
+        read_optional_records::<ReviewerVote>(&path.join(REVIEWER_VOTES_FILE)).await?;
+    let votes = read_records::<Vote>(&path.join(VOTES_FILE)).await?;
+    let images = read_records::<Image>(&path.join(IMAGES_FILE)).await?;
+    let posts = read_records::<Post>(&path.join(POSTS_FILE)).await?;
+    let reposts = read_records::<Repost>(&path.join(REPOSTS_FILE)).await?;
+    let audit_entries = read_optional_records::<AuditEntry>(&path.join(AUDIT_LOG_FILE)).await?;
+
+    let mut inserted = RecordCounts::default();
+    let mut transaction = database.begin().await?;
+
+    // Archived IDs to the ones the records got in this database.
+    let mut submission_ids = HashMap::new();
+    let mut vote_ids = HashMap::new();
+
+    for submission in submissions {
+        let archived_id = submission.submission_id;
+        let imported_submission =
+            Submission::import_submission(&mut *transaction, submission).await?;
+
+        submission_ids.insert(archived_id, imported_submission.id);
+        inserted.submissions += usize::from(imported_submission.inserted);
+    }
+
+    for mut vote in votes {
+        let archived_id = vote.vote_id;
+        vote.submission_id = map_id(&submission_ids, vote.submission_id, "vote")?;
+        let imported_vote = Vote::import_vote(&mut *transaction, vote).await?;
+
+        vote_ids.insert(archived_id, imported_vote.id);
+        inserted.votes += usize::from(imported_vote.inserted);
+    }
+
+    for mut image in images {
+        image.submission_id = map_id(&submission_ids, image.submission_id, "image")?;
+        image.vote_id = image
+            .vote_id
+            .map(|vote_id| {
+                vote_ids.get(&vote_id).copied().ok_or_else(|| {
+                    anyhow::anyhow!("the archive has an image of vote {vote_id}, which isn't in it")
+                })
+            })
+            .transpose()?;
+
+        // Stored files are named after the image, so they're only set once
+        // the image has its new ID.
+        let archived_files = image
+            .original_path
+            .take()
+            .zip(image.processed_path.take())
+            .filter(|_| manifest.media);
+
+        let imported_image = Image::import_image(&mut *transaction, image).await?;
+        if !imported_image.inserted {
+            continue;
+        }
+        inserted.images += 1;
+
+        if let Some((original_path, processed_path)) = archived_files {
+            match import_image_files(
+                path,
+                media_storage_path,
+                imported_image.id,
+                &original_path,
+                &processed_path,
+            )
+            .await
+            {
+                Ok(image_files) => {
+                    Image::update_image_files(&mut *transaction, imported_image.id, image_files)
+                        .await?;
+                }
+                Err(error) => {
+                    warn!(
+                        "couldn't import the files of image {image_id}, they will be downloaded again: {error:#?}",
+                        image_id = imported_image.id
+                    );
+                }
+            }
+        }
+    }
+
+    for mut pending_approval in pending_approvals {
+        pending_approval.submission_id = map_id(
+            &submission_ids,
+            pending_approval.submission_id,
+            "pending approval",
+        )?;
+
+        if PendingApproval::import_pending_approval(&mut *transaction, pending_approval).await? {
+            inserted.pending_approvals += 1;
+        }
+    }
+
+    for mut reviewer_vote in reviewer_votes {
+        reviewer_vote.submission_id = map_id(
+            &submission_ids,
+            reviewer_vote.submission_id,
+            "reviewer vote",
+        )?;
+
+        if ReviewerVote::import_reviewer_vote(&mut *transaction, reviewer_vote).await? {
+            inserted.reviewer_votes += 1;
+        }
+    }
+
+    for mut post in posts {
+        post.submission_id = map_id(&submission_ids, post.submission_id, "post")?;
+
+        if Post::import_post(&mut *transaction, post).await? {
+            inserted.posts += 1;
+        }
+    }
+
+    for mut repost in reposts {
+        repost.submission_id = map_id(&submission_ids, repost.submission_id, "repost")?;
+
+        if Repost::import_repost(&mut *transaction, repost).await? {
+            inserted.reposts += 1;
+        }
+    }
+
+    // Entries are added in the order they were made, so they keep it even
+    // though they get new IDs.
+    for mut audit_entry in audit_entries {
+        audit_entry.submission_id = audit_entry
+            .submission_id
+            .map(|submission_id| map_id(&submission_ids, submission_id, "audit entry"))
+            .transpose()?;
+
+        if AuditEntry::import_audit_entry(&mut *transaction, audit_entry).await? {
+            inserted.audit_entries += 1;
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(ImportReport {
+        archived: manifest.records,
+        inserted,
+    })
+}
+
+fn map_id(
+    submission_ids: &HashMap<i32, i32>,
+    submission_id: i32,
+    record: &str,
+) -> anyhow::Result<i32> {
+    submission_ids.get(&submission_id).copied().ok_or_else(|| {
+        anyhow::anyhow!(
+            "the archive has a {record} of submission {submission_id}, which isn't in it"
+        )
+    })
+}
+
+/// Copies the archived files of an image into the media storage, named after
+/// its new ID like the media storage names them.
+async fn import_image_files(
+    archive_path: &Path,
+    media_storage_path: &Path,
+    image_id: i32,
+    original_path: &str,
+    processed_path: &str,
+) -> anyhow::Result<UpdateImageFiles> {
+    Ok(UpdateImageFiles {
+        original_path: import_file(
+            archive_path,
+            &media_storage_path.join(ORIGINALS_DIRECTORY),
+            ORIGINALS_DIRECTORY,
+            image_id,
+            original_path,
+        )
+        .await?,
+        processed_path: import_file(
+            archive_path,
+            &media_storage_path.join(PROCESSED_DIRECTORY),
+            PROCESSED_DIRECTORY,
+            image_id,
+            processed_path,
+        )
+        .await?,
+    })
+}
+
+async fn import_file(
+    archive_path: &Path,
+    storage_directory: &Path,
+    archive_directory: &str,
+    image_id: i32,
+    stored_path: &str,
+) -> anyhow::Result<String> {
+    let stored_path = Path::new(stored_path);
+    let file_name = stored_path.file_name().ok_or_else(|| {
+        anyhow::anyhow!("`{path}` has no file name", path = stored_path.display())
+    })?;
+
+    // Whatever follows the old image ID is kept, like the limits processed
+    // files were made for, so they're still reused.
+    let suffix = stored_path
+        .file_stem()
+        .and_then(|file_stem| file_stem.to_str())
+        .and_then(|file_stem| file_stem.find('-').map(|index| &file_stem[index..]))
+        .unwrap_or_default();
+    let mut imported_path = storage_directory.join(format!("{image_id}{suffix}"));
+    if let Some(extension) = stored_path.extension() {
+        imported_path.set_extension(extension);
+    }
+
+    copy_file(
+        &archive_path
+            .join(MEDIA_DIRECTORY)
+            .join(archive_directory)
+            .join(file_name),
+        &imported_path,
+    )
+    .await?;
+
+    Ok(imported_path.to_string_lossy().into_owned())
+}
+
+async fn write_records<T: Serialize + Sync>(path: &Path, records: &[T]) -> anyhow::Result<()> {
+    let mut lines = String::new();
+    for record in records {
+        lines.push_str(&serde_json::to_string(record)?);
+        lines.push('\n');
+    }
+
+    tokio::fs::write(path, lines).await?;
+    Ok(())
+}
+
+async fn read_records<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Vec<T>> {
+    let lines = tokio::fs::read_to_string(path).await?;
+
+    lines
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|error| {
+                anyhow::anyhow!(
+                    "line {line_number} of `{path}` isn't a valid record: {error}",
+                    line_number = index + 1,
+                    path = path.display()
+                )
+            })
+        })
+        .collect()
+}
+
+/// Like [`read_records`], for records that older archives don't have a file
+/// of.
+async fn read_optional_records<T: DeserializeOwned>(path: &Path) -> anyhow::Result<Vec<T>> {
+    if !tokio::fs::try_exists(path).await? {
+        return Ok(vec![]);
+    }
+
+    read_records(path).await
+}
+
+async fn copy_file(from: &Path, to: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::copy(from, to).await?;
+
+    Ok(())
+}
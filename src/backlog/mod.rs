@@ -0,0 +1,323 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+use crate::{
+    media::YuriMedia,
+    models::{
+        audit_log::{AddAuditEntry, AuditAction, AuditEntry, AuditEntryHelpers},
+        images::{AddImages, Image, ImageHelpers, MAX_SUBMISSION_IMAGES},
+        submissions::{AddSubmission, Submission, SubmissionHelpers, SubmissionIds},
+    },
+};
+
+// Hosts that serve the same posts as another one.
+const HOST_ALIASES: &[(&str, &str)] = &[
+    ("twitter.com", "x.com"),
+    ("fxtwitter.com", "x.com"),
+    ("vxtwitter.com", "x.com"),
+    ("fixupx.com", "x.com"),
+];
+const HOST_PREFIXES: &[&str] = &["www.", "m.", "mobile."];
+// Query parameters that only track where a link was shared from.
+const TRACKING_PARAMETERS: &[&str] = &["fbclid", "igshid", "ref_src", "si"];
+
+/// Images of a row are separated with this, since paths may have spaces.
+const IMAGE_SEPARATOR: char = '|';
+
+#[derive(Debug, Deserialize)]
+struct BacklogRow {
+    artist: String,
+    art_link: String,
+    #[serde(alias = "images", alias = "image_path", alias = "image_url")]
+    image: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    alt_text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct SkippedRow {
+    line: u64,
+    artist: String,
+    art_link: String,
+    reason: String,
+}
+
+enum BacklogImage {
+    Url(String),
+    File(PathBuf),
+}
+
+#[derive(Debug)]
+pub struct BacklogReport {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// Puts links to the same art in the same form, so they can be compared.
+/// Returns [`None`] for anything that isn't a web link.
+pub fn normalize_link(link: &str) -> Option<String> {
+    let mut url = reqwest::Url::parse(link.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    url.set_scheme("https").ok()?;
+    url.set_port(None).ok()?;
+    url.set_fragment(None);
+
+    let host = url.host_str()?.to_lowercase();
+    let host = HOST_PREFIXES
+        .iter()
+        .find_map(|prefix| host.strip_prefix(prefix))
+        .unwrap_or(&host);
+    let host = HOST_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == host)
+        .map_or(host, |(_, host)| host)
+        .to_string();
+    url.set_host(Some(&host)).ok()?;
+
+    let query = url
+        .query_pairs()
+        .filter(|(name, _)| {
+            // Nothing in the query of a post on X changes what it links to.
+            host != "x.com"
+                && !name.starts_with("utm_")
+                && !TRACKING_PARAMETERS.contains(&name.as_ref())
+        })
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect::<Vec<_>>();
+    if query.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(query);
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+
+    Some(url.to_string())
+}
+
+/// Adds every row of a CSV backlog as an approved submission by
+/// `system_user_id`. Rows that can't be imported are written to
+/// `report_path` with the reason, as long as there are any.
+pub async fn import_backlog(
+    database: &PgPool,
+    media: &YuriMedia,
+    system_user_id: u64,
+    path: &Path,
+    report_path: &Path,
+) -> anyhow::Result<BacklogReport> {
+    let backlog = tokio::fs::read(path).await?;
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(backlog.as_slice());
+    // Spreadsheets tend to have headers like "Art Link".
+    let headers = reader
+        .headers()?
+        .iter()
+        .map(|header| header.to_lowercase().replace([' ', '-'], "_"))
+        .collect::<csv::StringRecord>();
+    // Relative image paths are relative to the backlog.
+    let backlog_directory = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut art_links = Submission::get_art_links(database)
+        .await?
+        .iter()
+        .filter_map(|art_link| normalize_link(art_link))
+        .collect::<HashSet<_>>();
+
+    let mut imported = 0;
+    let mut skipped_rows = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, csv::Position::line);
+
+        let row = match record.deserialize::<BacklogRow>(Some(&headers)) {
+            Ok(row) => row,
+            Err(error) => {
+                skipped_rows.push(SkippedRow {
+                    line,
+                    artist: String::new(),
+                    art_link: String::new(),
+                    reason: format!("the row can't be read: {error}"),
+                });
+                continue;
+            }
+        };
+
+        match import_row(
+            database,
+            media,
+            system_user_id,
+            backlog_directory,
+            &row,
+            &mut art_links,
+        )
+        .await
+        {
+            Ok(submission_id) => {
+                debug!(
+                    "imported line {line} of the backlog with: `submission_id`: {submission_id}"
+                );
+                imported += 1;
+            }
+            Err(error) => skipped_rows.push(SkippedRow {
+                line,
+                artist: row.artist,
+                art_link: row.art_link,
+                reason: format!("{error:#}"),
+            }),
+        }
+    }
+
+    if !skipped_rows.is_empty() {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for skipped_row in &skipped_rows {
+            writer.serialize(skipped_row)?;
+        }
+        tokio::fs::write(report_path, writer.into_inner()?).await?;
+    }
+
+    Ok(BacklogReport {
+        imported,
+        skipped: skipped_rows.len(),
+    })
+}
+
+async fn import_row(
+    database: &PgPool,
+    media: &YuriMedia,
+    system_user_id: u64,
+    backlog_directory: &Path,
+    row: &BacklogRow,
+    art_links: &mut HashSet<String>,
+) -> anyhow::Result<i32> {
+    if row.artist.is_empty() {
+        anyhow::bail!("there's no artist");
+    }
+
+    let art_link = normalize_link(&row.art_link)
+        .ok_or_else(|| anyhow::anyhow!("the art link isn't a web link"))?;
+    // Also catches rows repeated in the backlog itself.
+    if art_links.contains(&art_link) {
+        anyhow::bail!("the art was already submitted");
+    }
+
+    let mut images = vec![];
+    for image in row
+        .image
+        .split(IMAGE_SEPARATOR)
+        .map(str::trim)
+        .filter(|image| !image.is_empty())
+    {
+        images.push(backlog_image(backlog_directory, image).await?);
+    }
+    if images.is_empty() {
+        anyhow::bail!("there's no image");
+    }
+    if images.len() > MAX_SUBMISSION_IMAGES {
+        anyhow::bail!("there are more than {MAX_SUBMISSION_IMAGES} images");
+    }
+
+    // Empty ones are kept, so the rest still line up with their images.
+    let alt_texts = row
+        .alt_text
+        .split(IMAGE_SEPARATOR)
+        .map(|alt_text| Some(alt_text.trim().to_string()).filter(|alt_text| !alt_text.is_empty()))
+        .collect::<Vec<_>>();
+    if alt_texts.len() > images.len() {
+        anyhow::bail!("there are more alt texts than images");
+    }
+
+    let tags = row
+        .tags
+        .split([',', ';'])
+        .map(|tag| tag.trim().trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .collect::<Vec<_>>();
+
+    let mut transaction = database.begin().await?;
+
+    let submission = Submission::add_submission(
+        &mut *transaction,
+        AddSubmission {
+            user_id: system_user_id,
+            artist: row.artist.clone(),
+            art_link: art_link.clone(),
+            // Submissions don't have tags of their own, so they're kept
+            // where the team can see them.
+            additional_information: Some(format!("Tags: {tags}", tags = tags.join(", ")))
+                .filter(|_| !tags.is_empty()),
+            content_warning: None,
+        },
+    )
+    .await?;
+
+    let added_images = Image::add_images(
+        &mut *transaction,
+        AddImages {
+            submission_id: submission.submission_id,
+            image_paths: images
+                .iter()
+                .map(|image| match image {
+                    BacklogImage::Url(url) => url.clone(),
+                    BacklogImage::File(path) => reqwest::Url::from_file_path(path)
+                        .map_or_else(|()| path.to_string_lossy().into_owned(), String::from),
+                })
+                .collect(),
+            alt_texts,
+        },
+    )
+    .await?;
+    for (added_image, image) in added_images.iter().zip(&images) {
+        if let BacklogImage::File(path) = image {
+            let original_path = media.store_original(added_image.image_id, path).await?;
+            Image::set_original_path(&mut *transaction, added_image.image_id, original_path)
+                .await?;
+        }
+    }
+
+    Submission::approve_submission(
+        &mut *transaction,
+        SubmissionIds::SubmissionId(submission.submission_id),
+    )
+    .await?;
+    AuditEntry::add_audit_entry(
+        &mut *transaction,
+        AddAuditEntry {
+            actor_id: None,
+            action: AuditAction::ImportBacklog,
+            submission_id: Some(submission.submission_id),
+            before_value: None,
+            after_value: Some(String::from("approved")),
+        },
+    )
+    .await?;
+
+    transaction.commit().await?;
+
+    art_links.insert(art_link);
+    Ok(submission.submission_id)
+}
+
+async fn backlog_image(backlog_directory: &Path, image: &str) -> anyhow::Result<BacklogImage> {
+    if let Ok(url) = reqwest::Url::parse(image) {
+        if matches!(url.scheme(), "http" | "https") {
+            return Ok(BacklogImage::Url(url.to_string()));
+        }
+    }
+
+    match tokio::fs::canonicalize(backlog_directory.join(image)).await {
+        Ok(path) => Ok(BacklogImage::File(path)),
+        Err(_) => anyhow::bail!("`{image}` isn't a link or an existing file"),
+    }
+}
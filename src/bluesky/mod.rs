@@ -0,0 +1,761 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use reqwest::{Client, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    config::{BlueskyConfig, BlueskyLabel},
+    media::{self, Media},
+    publisher::{Publication, PublishError, Published, Publisher, RetryPolicy},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+// Access tokens are valid for a couple of hours, a new session is created a
+// while before that so a request never races the expiry.
+const SESSION_LIFETIME: Duration = Duration::from_secs(60 * 60);
+
+// Limits of `app.bsky.embed.images`, the app also scales images down to fit
+// in 2000x2000 pixels.
+const IMAGE_SIZE_LIMIT: u64 = 1_000_000;
+const IMAGE_MATRIX_LIMIT: u64 = 2000 * 2000;
+const SUPPORTED_MIME_TYPES: &[&str] = &["image/jpeg", "image/png", "image/webp"];
+
+// Limits of the text of `app.bsky.feed.post`.
+const MAX_POST_GRAPHEMES: usize = 300;
+const MAX_POST_BYTES: usize = 3000;
+
+// Alphabet of the base32 encoding used by TIDs, the record keys of posts.
+const TID_ALPHABET: &[u8; 32] = b"234567abcdefghijklmnopqrstuvwxyz";
+
+pub struct YuriBluesky {
+    client: Client,
+    retry_policy: RetryPolicy,
+    service: String,
+    identifier: String,
+    app_password: String,
+    content_warning_label: Option<BlueskyLabel>,
+    session: Mutex<Option<(Session, Instant)>>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    access_jwt: String,
+    did: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadedBlob {
+    blob: Value,
+}
+
+/// What's stored as the media ID of an uploaded image, since the alt text is
+/// only sent with the post record on Bluesky.
+#[derive(Debug, Serialize, Deserialize)]
+struct EmbeddedImage {
+    alt: String,
+    image: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct CreatedRecord {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Records {
+    records: Vec<Record>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Record {
+    uri: String,
+    value: Value,
+}
+
+impl YuriBluesky {
+    pub fn new(bluesky_config: BlueskyConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            retry_policy: RetryPolicy::new(&bluesky_config.retry),
+            service: bluesky_config.service.trim_end_matches('/').to_string(),
+            identifier: bluesky_config.identifier,
+            app_password: bluesky_config.app_password,
+            content_warning_label: bluesky_config.content_warning_label,
+            session: Mutex::new(None),
+        })
+    }
+
+    async fn session(&self) -> anyhow::Result<Session> {
+        let mut session = self.session.lock().await;
+        if let Some((session, created)) = &*session {
+            if created.elapsed() < SESSION_LIFETIME {
+                return Ok(session.clone());
+            }
+        }
+
+        debug!("creating a new Bluesky session");
+
+        let new_session = self
+            .retry_policy
+            .send(|| {
+                self.client
+                    .post(self.endpoint("com.atproto.server.createSession"))
+                    .json(&json!({
+                        "identifier": self.identifier,
+                        "password": self.app_password,
+                    }))
+                    .send()
+            })
+            .await?
+            .json::<Session>()
+            .await?;
+
+        *session = Some((new_session.clone(), Instant::now()));
+        drop(session);
+
+        Ok(new_session)
+    }
+
+    /// Sends a request with the cached session, creating a new session and
+    /// sending it once more if the cached one turns out to have expired.
+    async fn send_authenticated<F, Fut>(&self, send: F) -> anyhow::Result<(Session, Response)>
+    where
+        F: Fn(&Session) -> Fut + Sync,
+        Fut: Future<Output = reqwest::Result<Response>> + Send,
+    {
+        let session = self.session().await?;
+        match self.retry_policy.send(|| send(&session)).await {
+            Ok(response) => Ok((session, response)),
+            Err(error) if is_expired_session(&error) => {
+                debug!("the Bluesky session expired, creating a new one");
+
+                self.forget_session(&session).await;
+                let session = self.session().await?;
+                let response = self.retry_policy.send(|| send(&session)).await?;
+
+                Ok((session, response))
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Drops the cached session, unless it was already replaced by a newer
+    /// one.
+    async fn forget_session(&self, expired_session: &Session) {
+        let mut session = self.session.lock().await;
+        if session
+            .as_ref()
+            .is_some_and(|(session, _)| session.access_jwt == expired_session.access_jwt)
+        {
+            *session = None;
+        }
+    }
+
+    /// Gets our post with the record key, if there is one.
+    async fn get_post_record(&self, record_key: &str) -> anyhow::Result<Option<(Session, Record)>> {
+        let result = self
+            .send_authenticated(|session| {
+                self.client
+                    .get(self.endpoint("com.atproto.repo.getRecord"))
+                    .bearer_auth(&session.access_jwt)
+                    .query(&[
+                        ("repo", session.did.as_str()),
+                        ("collection", "app.bsky.feed.post"),
+                        ("rkey", record_key),
+                    ])
+                    .send()
+            })
+            .await;
+
+        match result {
+            Ok((session, response)) => Ok(Some((session, response.json::<Record>().await?))),
+            // Missing records are a `400 Bad Request` with a `RecordNotFound`
+            // error.
+            Err(error)
+                if error
+                    .downcast_ref::<PublishError>()
+                    .is_some_and(PublishError::is_permanent) =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn endpoint(&self, method: &str) -> String {
+        format!("{service}/xrpc/{method}", service = self.service)
+    }
+
+    fn post_url(session: &Session, uri: &str) -> Option<String> {
+        uri.rsplit('/').next().map(|record_key| {
+            format!(
+                "https://bsky.app/profile/{did}/post/{record_key}",
+                did = session.did
+            )
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Publisher for YuriBluesky {
+    fn target(&self) -> &'static str {
+        "bluesky"
+    }
+
+    async fn upload_media(
+        &self,
+        media: &Media,
+        description: Option<&str>,
+    ) -> anyhow::Result<String> {
+        if media.mime_type.starts_with("video/") {
+            anyhow::bail!("videos can't be posted to Bluesky");
+        }
+
+        let fitted_media;
+        let media = if media.bytes.len() as u64 > IMAGE_SIZE_LIMIT
+            || !SUPPORTED_MIME_TYPES.contains(&media.mime_type.as_str())
+        {
+            fitted_media = media::fit(
+                media,
+                IMAGE_SIZE_LIMIT,
+                IMAGE_MATRIX_LIMIT,
+                SUPPORTED_MIME_TYPES,
+            )
+            .await?;
+            &fitted_media
+        } else {
+            media
+        };
+
+        debug!(
+            "uploading a `{mime_type}` blob to Bluesky",
+            mime_type = media.mime_type
+        );
+
+        let (_, response) = self
+            .send_authenticated(|session| {
+                self.client
+                    .post(self.endpoint("com.atproto.repo.uploadBlob"))
+                    .bearer_auth(&session.access_jwt)
+                    .header(reqwest::header::CONTENT_TYPE, &media.mime_type)
+                    .body(media.bytes.clone())
+                    .send()
+            })
+            .await?;
+        let uploaded_blob = response.json::<UploadedBlob>().await?;
+
+        Ok(serde_json::to_string(&EmbeddedImage {
+            alt: description.unwrap_or_default().to_string(),
+            image: uploaded_blob.blob,
+        })?)
+    }
+
+    async fn publish(
+        &self,
+        publication: &Publication,
+        idempotency_key: uuid::Uuid,
+    ) -> anyhow::Result<Published> {
+        debug!("publishing a post to Bluesky");
+
+        // Longer posts are rejected, and sending them again won't change that.
+        let graphemes = publication.text.graphemes(true).count();
+        if graphemes > MAX_POST_GRAPHEMES || publication.text.len() > MAX_POST_BYTES {
+            return Err(PublishError::Permanent {
+                message: format!(
+                    "the post is {graphemes} characters and {bytes} bytes long, Bluesky allows up to {MAX_POST_GRAPHEMES} characters and {MAX_POST_BYTES} bytes",
+                    bytes = publication.text.len()
+                ),
+                status: None,
+            }
+            .into());
+        }
+
+        let mut record = json!({
+            "$type": "app.bsky.feed.post",
+            "text": publication.text,
+            "createdAt": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "facets": facets(publication),
+        });
+        if !publication.media_ids.is_empty() {
+            record["embed"] = json!({
+                "$type": "app.bsky.embed.images",
+                "images": publication
+                    .media_ids
+                    .iter()
+                    .map(|media_id| serde_json::from_str::<EmbeddedImage>(media_id))
+                    .collect::<Result<Vec<_>, _>>()?,
+            });
+        }
+        if let Some(label) = self.content_warning_label.filter(|_| publication.sensitive) {
+            record["labels"] = json!({
+                "$type": "com.atproto.label.defs#selfLabels",
+                "values": [{ "val": label }],
+            });
+        }
+
+        // Records don't take idempotency keys, but their keys can be picked.
+        // Sending the post again with the same key is rejected, so an attempt
+        // that got through before is found by its key instead.
+        let record_key = record_key(idempotency_key);
+        let result = self
+            .send_authenticated(|session| {
+                self.client
+                    .post(self.endpoint("com.atproto.repo.createRecord"))
+                    .bearer_auth(&session.access_jwt)
+                    .json(&json!({
+                        "repo": session.did,
+                        "collection": "app.bsky.feed.post",
+                        "rkey": record_key,
+                        "record": record,
+                    }))
+                    .send()
+            })
+            .await;
+
+        let (session, created_record) = match result {
+            Ok((session, response)) => (session, response.json::<CreatedRecord>().await?),
+            Err(error)
+                if error
+                    .downcast_ref::<PublishError>()
+                    .is_some_and(PublishError::is_permanent) =>
+            {
+                let Some((session, existing_record)) = self.get_post_record(&record_key).await?
+                else {
+                    return Err(error);
+                };
+
+                debug!(
+                    "a post with the record key `{record_key}` was already published to Bluesky"
+                );
+                (
+                    session,
+                    CreatedRecord {
+                        uri: existing_record.uri,
+                    },
+                )
+            }
+            Err(error) => return Err(error),
+        };
+
+        debug!(
+            "published a post to Bluesky with: `uri`: {uri}",
+            uri = created_record.uri
+        );
+        Ok(Published {
+            url: Self::post_url(&session, &created_record.uri),
+            id: created_record.uri,
+        })
+    }
+
+    async fn find_published(&self, media_ids: &[String]) -> anyhow::Result<Option<Published>> {
+        // Records don't have idempotency keys, so only posts with images can
+        // be matched up by the content IDs of their blobs.
+        let blob_links = media_ids
+            .iter()
+            .filter_map(|media_id| serde_json::from_str::<EmbeddedImage>(media_id).ok())
+            .filter_map(|embedded_image| blob_link(&embedded_image.image))
+            .collect::<Vec<_>>();
+        if blob_links.is_empty() {
+            return Ok(None);
+        }
+
+        let (session, response) = self
+            .send_authenticated(|session| {
+                self.client
+                    .get(self.endpoint("com.atproto.repo.listRecords"))
+                    .bearer_auth(&session.access_jwt)
+                    .query(&[
+                        ("repo", session.did.as_str()),
+                        ("collection", "app.bsky.feed.post"),
+                        ("limit", "50"),
+                    ])
+                    .send()
+            })
+            .await?;
+        let records = response.json::<Records>().await?;
+
+        Ok(records
+            .records
+            .into_iter()
+            .find(|record| {
+                record.value["embed"]["images"]
+                    .as_array()
+                    .is_some_and(|images| {
+                        images.iter().any(|image| {
+                            blob_link(&image["image"])
+                                .is_some_and(|link| blob_links.contains(&link))
+                        })
+                    })
+            })
+            .map(|record| Published {
+                url: Self::post_url(&session, &record.uri),
+                id: record.uri,
+            }))
+    }
+}
+
+/// Access tokens that ran out are rejected with a `400 Bad Request` and an
+/// `ExpiredToken` error rather than a `401 Unauthorized` by some servers.
+fn is_expired_session(error: &PublishError) -> bool {
+    match error.status() {
+        Some(StatusCode::UNAUTHORIZED) => true,
+        Some(StatusCode::BAD_REQUEST) => error.message().contains("ExpiredToken"),
+        _ => false,
+    }
+}
+
+/// Turns an idempotency key into a TID, the kind of record key posts have,
+/// so it's the same for every attempt at a post. TIDs are the microseconds
+/// since the Unix epoch followed by a 10 bit clock ID, which is taken from the
+/// random bits of the key.
+fn record_key(idempotency_key: uuid::Uuid) -> String {
+    // Keys from before they carried a time get the current one, so retries
+    // of those can't be matched up with an earlier attempt.
+    let microseconds = idempotency_key.get_timestamp().map_or_else(
+        || chrono::Utc::now().timestamp_micros().unsigned_abs(),
+        |timestamp| {
+            let (seconds, nanoseconds) = timestamp.to_unix();
+            seconds * 1_000_000 + u64::from(nanoseconds / 1000)
+        },
+    );
+    let clock_id = idempotency_key.as_u64_pair().1 & 0x3ff;
+
+    // The top bit of a TID is always zero.
+    let mut bits = ((microseconds << 10) | clock_id) & (u64::MAX >> 1);
+    let mut record_key = [0; 13];
+    for character in record_key.iter_mut().rev() {
+        *character = TID_ALPHABET[(bits & 31) as usize];
+        bits >>= 5;
+    }
+
+    String::from_utf8_lossy(&record_key).into_owned()
+}
+
+fn blob_link(blob: &Value) -> Option<String> {
+    blob["ref"]["$link"].as_str().map(ToString::to_string)
+}
+
+/// Rich text facets for the source link and hashtags, which Bluesky doesn't
+/// detect on its own. Their ranges are in UTF-8 bytes.
+fn facets(publication: &Publication) -> Vec<Value> {
+    let mut facets = vec![];
+
+    if let Some(source_link) = &publication.source_link {
+        if let Some(start) = publication.text.find(source_link.as_str()) {
+            facets.push(json!({
+                "index": { "byteStart": start, "byteEnd": start + source_link.len() },
+                "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": source_link }],
+            }));
+        }
+    }
+
+    let mut offset = 0;
+    for word in publication.text.split_inclusive(char::is_whitespace) {
+        let tag = word.trim_end();
+        if let Some(name) = tag.strip_prefix('#').filter(|name| !name.is_empty()) {
+            facets.push(json!({
+                "index": { "byteStart": offset, "byteEnd": offset + tag.len() },
+                "features": [{ "$type": "app.bsky.richtext.facet#tag", "tag": name }],
+            }));
+        }
+        offset += word.len();
+    }
+
+    facets
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        extract::{RawQuery, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+        Json, Router,
+    };
+
+    use super::*;
+    use crate::config::RetryConfig;
+
+    /// A PDS that knows one account and its posts.
+    #[derive(Default)]
+    struct MockPds {
+        sessions_created: usize,
+        /// Whether the first session's token is rejected as expired.
+        expire_first_session: bool,
+        /// Whether the first post is created but answered with an error.
+        fail_first_create: bool,
+        create_requests: usize,
+        records: HashMap<String, Value>,
+    }
+
+    type SharedPds = Arc<Mutex<MockPds>>;
+
+    async fn create_session(State(pds): State<SharedPds>) -> Json<Value> {
+        let mut pds = pds.lock().unwrap();
+        pds.sessions_created += 1;
+
+        Json(json!({
+            "accessJwt": format!("token-{sessions}", sessions = pds.sessions_created),
+            "did": "did:plc:yuri",
+        }))
+    }
+
+    async fn create_record(
+        State(pds): State<SharedPds>,
+        headers: HeaderMap,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        let mut pds = pds.lock().unwrap();
+        pds.create_requests += 1;
+
+        if pds.expire_first_session && headers["authorization"] == "Bearer token-1" {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "ExpiredToken", "message": "Token has expired" })),
+            );
+        }
+
+        let record_key = body["rkey"].as_str().unwrap().to_string();
+        if pds.records.contains_key(&record_key) {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "InvalidRequest", "message": "Record already exists" })),
+            );
+        }
+
+        let uri = format!("at://did:plc:yuri/app.bsky.feed.post/{record_key}");
+        pds.records.insert(record_key, body["record"].clone());
+
+        if std::mem::take(&mut pds.fail_first_create) {
+            return (StatusCode::BAD_GATEWAY, Json(json!({})));
+        }
+
+        (StatusCode::OK, Json(json!({ "uri": uri, "cid": "cid" })))
+    }
+
+    async fn get_record(
+        State(pds): State<SharedPds>,
+        RawQuery(query): RawQuery,
+    ) -> (StatusCode, Json<Value>) {
+        let pds = pds.lock().unwrap();
+        let record_key = query
+            .unwrap_or_default()
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("rkey=").map(ToString::to_string))
+            .unwrap_or_default();
+
+        match pds.records.get(&record_key) {
+            Some(record) => (
+                StatusCode::OK,
+                Json(json!({
+                    "uri": format!("at://did:plc:yuri/app.bsky.feed.post/{record_key}"),
+                    "value": record,
+                })),
+            ),
+            None => (
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "RecordNotFound" })),
+            ),
+        }
+    }
+
+    async fn mock_bluesky(pds: MockPds) -> (YuriBluesky, SharedPds) {
+        let pds = Arc::new(Mutex::new(pds));
+        let app = Router::new()
+            .route(
+                "/xrpc/com.atproto.server.createSession",
+                post(create_session),
+            )
+            .route("/xrpc/com.atproto.repo.createRecord", post(create_record))
+            .route("/xrpc/com.atproto.repo.getRecord", get(get_record))
+            .with_state(Arc::clone(&pds));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let bluesky = YuriBluesky::new(BlueskyConfig {
+            service: format!("http://{address}"),
+            identifier: "yuri.bsky.social".to_string(),
+            app_password: "app-password".to_string(),
+            content_warning_label: Some(BlueskyLabel::GraphicMedia),
+            retry: RetryConfig {
+                max_attempts: 3,
+                base_delay_ms: 1,
+                max_delay_secs: 5,
+            },
+        })
+        .unwrap();
+
+        (bluesky, pds)
+    }
+
+    fn publication() -> Publication {
+        Publication {
+            text: "Art by someone\nhttps://example.com/art".to_string(),
+            source_link: Some("https://example.com/art".to_string()),
+            media_ids: vec![],
+            content_warning: None,
+            sensitive: false,
+            repost: false,
+        }
+    }
+
+    #[test]
+    fn record_keys_are_deterministic_tids() {
+        let idempotency_key = uuid::Uuid::now_v7();
+        let record_key = record_key(idempotency_key);
+
+        assert_eq!(record_key, super::record_key(idempotency_key));
+        assert_eq!(record_key.len(), 13);
+        assert!("234567abcdefghij".contains(&record_key[..1]));
+        assert!(record_key
+            .bytes()
+            .all(|character| TID_ALPHABET.contains(&character)));
+    }
+
+    #[test]
+    fn record_keys_carry_the_time_of_the_idempotency_key() {
+        let idempotency_key = uuid::Uuid::now_v7();
+        let tid = record_key(idempotency_key)
+            .bytes()
+            .fold(0, |tid, character| {
+                (tid << 5)
+                    | TID_ALPHABET
+                        .iter()
+                        .position(|alphabet_character| *alphabet_character == character)
+                        .unwrap() as u64
+            });
+
+        let (seconds, nanoseconds) = idempotency_key.get_timestamp().unwrap().to_unix();
+        assert_eq!(
+            tid >> 10,
+            seconds * 1_000_000 + u64::from(nanoseconds / 1000)
+        );
+    }
+
+    #[tokio::test]
+    async fn publishes_once_when_a_created_post_is_retried() {
+        let (bluesky, pds) = mock_bluesky(MockPds {
+            fail_first_create: true,
+            ..MockPds::default()
+        })
+        .await;
+        let idempotency_key = uuid::Uuid::now_v7();
+
+        let published = bluesky
+            .publish(&publication(), idempotency_key)
+            .await
+            .unwrap();
+
+        let pds = pds.lock().unwrap();
+        assert_eq!(pds.create_requests, 2);
+        assert_eq!(pds.records.len(), 1);
+        assert_eq!(
+            published.id,
+            format!(
+                "at://did:plc:yuri/app.bsky.feed.post/{record_key}",
+                record_key = record_key(idempotency_key)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn publishing_again_finds_the_earlier_post() {
+        let (bluesky, pds) = mock_bluesky(MockPds::default()).await;
+        let idempotency_key = uuid::Uuid::now_v7();
+
+        let published = bluesky
+            .publish(&publication(), idempotency_key)
+            .await
+            .unwrap();
+        let published_again = bluesky
+            .publish(&publication(), idempotency_key)
+            .await
+            .unwrap();
+
+        assert_eq!(published.id, published_again.id);
+        assert_eq!(pds.lock().unwrap().records.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn labels_posts_with_a_content_warning() {
+        let (bluesky, pds) = mock_bluesky(MockPds::default()).await;
+
+        bluesky
+            .publish(
+                &Publication {
+                    content_warning: Some("Blood".to_string()),
+                    sensitive: true,
+                    ..publication()
+                },
+                uuid::Uuid::now_v7(),
+            )
+            .await
+            .unwrap();
+
+        let pds = pds.lock().unwrap();
+        let record = pds.records.values().next().unwrap();
+        assert_eq!(record["labels"]["values"][0]["val"], "graphic-media");
+    }
+
+    #[tokio::test]
+    async fn refuses_posts_over_the_length_limit() {
+        let (bluesky, pds) = mock_bluesky(MockPds::default()).await;
+
+        let error = bluesky
+            .publish(
+                &Publication {
+                    // Each flag is a single grapheme made of four characters.
+                    text: "🏳️‍🌈".repeat(MAX_POST_GRAPHEMES + 1),
+                    ..publication()
+                },
+                uuid::Uuid::now_v7(),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(error
+            .downcast_ref::<PublishError>()
+            .is_some_and(PublishError::is_permanent));
+        assert_eq!(pds.lock().unwrap().create_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn refreshes_an_expired_session_once() {
+        let (bluesky, pds) = mock_bluesky(MockPds {
+            expire_first_session: true,
+            ..MockPds::default()
+        })
+        .await;
+
+        bluesky
+            .publish(&publication(), uuid::Uuid::now_v7())
+            .await
+            .unwrap();
+
+        let pds = pds.lock().unwrap();
+        assert_eq!(pds.sessions_created, 2);
+        assert_eq!(pds.create_requests, 2);
+        assert_eq!(pds.records.len(), 1);
+    }
+}
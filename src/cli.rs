@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 
 #[derive(Parser, Debug, Serialize, Deserialize)]
@@ -12,16 +12,120 @@ pub struct Cli {
 pub enum SubCommands {
     /// Start the bot
     Start {
-        /// Path to the configuration file
-        #[arg(env, long, default_value_t = dirs::config_dir()
-            .expect("unsupported operating system or platform")
-            .join("YuriEveryDay_bot")
-            .join("config")
-            .with_extension("yaml")
-            .as_path()
-            .display()
-            .to_string()
-        )]
-        config: String,
+        #[command(flatten)]
+        config: ConfigArgs,
+        /// Refuse to start when there are pending migrations instead of
+        /// applying them
+        #[arg(long)]
+        require_migrated: bool,
     },
+    /// Control the posting scheduler
+    Post {
+        #[command(flatten)]
+        config: ConfigArgs,
+        #[command(subcommand)]
+        subcommand: PostSubCommands,
+    },
+    /// Manage database migrations
+    Migrate {
+        #[command(flatten)]
+        config: ConfigArgs,
+        #[command(subcommand)]
+        subcommand: MigrateSubCommands,
+    },
+    /// Export submissions, votes and posts to an archive
+    Export {
+        #[command(flatten)]
+        config: ConfigArgs,
+        /// Directory to write the archive to
+        path: std::path::PathBuf,
+        /// Also copy the stored image files into the archive
+        #[arg(long)]
+        media: bool,
+    },
+    /// Import an archive made with `export`, skipping what's already there
+    Import {
+        #[command(flatten)]
+        config: ConfigArgs,
+        /// Directory the archive was written to
+        path: std::path::PathBuf,
+    },
+    /// Add a CSV backlog of art as approved submissions
+    ///
+    /// The CSV needs `artist`, `art_link` and `image` columns, and can have
+    /// `tags` and `alt_text` columns. Several images, and the alt text of
+    /// each, are separated with `|`.
+    ImportBacklog {
+        #[command(flatten)]
+        config: ConfigArgs,
+        /// Path to the CSV file
+        path: std::path::PathBuf,
+        /// Where to write the rows that were skipped, next to the CSV file by
+        /// default
+        #[arg(long)]
+        report: Option<std::path::PathBuf>,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(flatten)]
+        config: ConfigArgs,
+        #[command(subcommand)]
+        subcommand: ConfigSubCommands,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum PostSubCommands {
+    /// Pause posting, submissions keep being queued
+    Pause {
+        /// Why posting is paused
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Resume posting
+    Resume,
+    /// Show whether posting is running or paused
+    Status,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum MigrateSubCommands {
+    /// Apply pending migrations
+    Run,
+    /// Show which migrations are applied and which are pending
+    Status,
+    /// Revert the latest migration
+    Revert {
+        /// Revert every migration newer than this version instead
+        #[arg(long)]
+        target: Option<i64>,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum ConfigSubCommands {
+    /// Report every missing or invalid field
+    Check {
+        /// Also check that Discord IDs look valid, without contacting Discord
+        #[arg(long)]
+        validate_ids: bool,
+        /// Print the effective configuration with secrets redacted
+        #[arg(long)]
+        print: bool,
+    },
+}
+
+#[derive(Args, Debug, Serialize, Deserialize)]
+pub struct ConfigArgs {
+    /// Path to the configuration file
+    #[arg(env, long, default_value_t = dirs::config_dir()
+        .expect("unsupported operating system or platform")
+        .join("YuriEveryDay_bot")
+        .join("config")
+        .with_extension("yaml")
+        .as_path()
+        .display()
+        .to_string()
+    )]
+    pub config: String,
 }
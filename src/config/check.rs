@@ -0,0 +1,252 @@
+use std::path::Path;
+
+use figment::Figment;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::scheduler::schedule::Schedule;
+
+use super::{
+    BlueskyConfig, Config, DatabaseConfig, DiscordConfig, FeedConfig, MastodonConfig, MediaConfig,
+    MisskeyConfig, SchedulerConfig, ServerConfig, SubmissionsConfig,
+};
+
+// Discord IDs store when they were created in their upper bits, as
+// milliseconds since the start of 2015.
+const DISCORD_EPOCH: u64 = 1_420_070_400_000;
+
+const SECRETS: &[&str] = &[
+    "/mastodon/access_token",
+    "/misskey/access_token",
+    "/bluesky/app_password",
+    "/discord/token",
+];
+const REDACTED: &str = "<redacted>";
+
+pub struct ConfigCheck {
+    /// Only there if the configuration could be loaded at all.
+    pub config: Option<Config>,
+    pub problems: Vec<String>,
+}
+
+/// Loads the configuration the same way [`Config::load`] does, but collects
+/// every problem it can find instead of stopping at the first one.
+pub fn check(path: &str, validate_ids: bool) -> ConfigCheck {
+    let figment = Config::figment(path);
+    let mut problems = vec![];
+
+    if !Path::new(path).exists() {
+        warn!("`{path}` doesn't exist, only environment variables are used");
+    }
+    for metadata in figment.metadata() {
+        debug!(
+            "reading configuration from: {name}{source}",
+            name = metadata.name,
+            source = metadata
+                .source
+                .as_ref()
+                .map(|source| format!(" ({source})"))
+                .unwrap_or_default()
+        );
+    }
+
+    let config = match figment.extract::<Config>() {
+        Ok(config) => Some(config),
+        Err(error) => {
+            // Serde stops at the first problem it finds, so every section is
+            // extracted on its own to find the problems in the others too.
+            check_section::<MastodonConfig>(&figment, &["mastodon"], true, &mut problems);
+            check_section::<MisskeyConfig>(&figment, &["misskey"], false, &mut problems);
+            check_section::<BlueskyConfig>(&figment, &["bluesky"], false, &mut problems);
+            check_section::<DiscordConfig>(&figment, &["discord"], true, &mut problems);
+            check_section::<DatabaseConfig>(&figment, &["database"], true, &mut problems);
+            check_section::<SchedulerConfig>(&figment, &["scheduler"], false, &mut problems);
+            check_section::<SubmissionsConfig>(&figment, &["submissions"], false, &mut problems);
+            check_section::<MediaConfig>(&figment, &["media"], false, &mut problems);
+            check_section::<FeedConfig>(&figment, &["feed"], false, &mut problems);
+            check_section::<ServerConfig>(&figment, &["server"], false, &mut problems);
+            check_section::<u64>(
+                &figment,
+                &["shutdown_timeout_secs", "shutdown-timeout-secs"],
+                false,
+                &mut problems,
+            );
+
+            if problems.is_empty() {
+                problems.extend(error.into_iter().map(|error| error.to_string()));
+            }
+
+            None
+        }
+    };
+
+    if let Some(config) = &config {
+        match Schedule::new(&config.scheduler) {
+            Ok(schedule) if schedule.next_after(chrono::Utc::now()).is_none() => {
+                problems.push(String::from(
+                    "posting schedule has no upcoming posts, the blackouts may cover all of it",
+                ));
+            }
+            Ok(_) => {}
+            Err(error) => problems.push(format!("{error:#} for key \"scheduler.schedule\"")),
+        }
+
+        check_quorums(&config.discord, &config.submissions, &mut problems);
+
+        if validate_ids {
+            check_ids(&config.discord, &config.submissions, &mut problems);
+        }
+    }
+
+    ConfigCheck { config, problems }
+}
+
+/// The configuration as it's used, with secrets replaced.
+pub fn redacted(config: &Config) -> anyhow::Result<Value> {
+    let mut value = serde_json::to_value(config)?;
+
+    for pointer in SECRETS {
+        if let Some(secret) = value.pointer_mut(pointer) {
+            *secret = Value::from(REDACTED);
+        }
+    }
+
+    if let Some(Value::String(url)) = value.pointer_mut("/database/url") {
+        *url = match reqwest::Url::parse(url) {
+            Ok(mut parsed_url) => {
+                if parsed_url.password().is_some() {
+                    parsed_url
+                        .set_password(Some("redacted"))
+                        .map_err(|()| anyhow::anyhow!("can't redact the database URL"))?;
+                }
+                parsed_url.to_string()
+            }
+            // Connection strings that aren't URLs may have a password
+            // anywhere in them.
+            Err(_) => String::from(REDACTED),
+        };
+    }
+
+    Ok(value)
+}
+
+fn check_section<T: DeserializeOwned>(
+    figment: &Figment,
+    keys: &[&str],
+    required: bool,
+    problems: &mut Vec<String>,
+) {
+    let Some(key) = keys.iter().find(|key| figment.contains(key)) else {
+        if required {
+            problems.push(format!(
+                "missing `{key}`, it isn't set in any source",
+                key = keys[0]
+            ));
+        }
+        return;
+    };
+
+    if let Err(error) = figment.extract_inner::<T>(key) {
+        problems.extend(error.into_iter().map(|mut error| {
+            // `extract_inner` puts the section's key last in the path.
+            if error.path.last().is_some_and(|last| last == key) {
+                error.path.rotate_right(1);
+            }
+            error.to_string()
+        }));
+    }
+}
+
+fn check_ids(
+    discord_config: &DiscordConfig,
+    submissions_config: &SubmissionsConfig,
+    problems: &mut Vec<String>,
+) {
+    check_id("discord.server_id", discord_config.server_id, problems);
+    check_id(
+        "discord.channels.approve_id",
+        discord_config.channels.approve_id,
+        problems,
+    );
+    check_id(
+        "discord.channels.vote_id",
+        discord_config.channels.vote_id,
+        problems,
+    );
+    if let Some(log_id) = discord_config.channels.log_id {
+        check_id("discord.channels.log_id", log_id, problems);
+    }
+
+    if let Some(system_user_id) = submissions_config.system_user_id {
+        check_id("submissions.system_user_id", system_user_id, problems);
+    }
+
+    if discord_config.team.is_empty() {
+        problems.push(String::from(
+            "`discord.team` is empty, nobody will be able to review submissions",
+        ));
+    }
+    for (index, user_id) in discord_config.team.iter().enumerate() {
+        check_id(&format!("discord.team[{index}]"), *user_id, problems);
+
+        if discord_config.team[..index].contains(user_id) {
+            problems.push(format!(
+                "`discord.team[{index}]` is `{user_id}`, which is listed more than once"
+            ));
+        }
+    }
+
+    for (index, user_id) in discord_config.admins.iter().enumerate() {
+        check_id(&format!("discord.admins[{index}]"), *user_id, problems);
+
+        if !discord_config.team.contains(user_id) {
+            problems.push(format!(
+                "`discord.admins[{index}]` is `{user_id}`, who isn't in `discord.team` and can't review submissions"
+            ));
+        }
+    }
+
+    if discord_config.channels.approve_id == discord_config.channels.vote_id {
+        problems.push(String::from(
+            "`discord.channels.approve_id` and `discord.channels.vote_id` are the same channel",
+        ));
+    }
+}
+
+/// Also checked whenever the configuration is loaded.
+pub fn check_quorums(
+    discord_config: &DiscordConfig,
+    submissions_config: &SubmissionsConfig,
+    problems: &mut Vec<String>,
+) {
+    for (key, quorum) in [
+        ("approval_quorum", submissions_config.approval_quorum),
+        ("rejection_quorum", submissions_config.rejection_quorum),
+    ] {
+        if quorum == 0 {
+            problems.push(format!(
+                "`submissions.{key}` is `0`, at least one vote is needed to decide a submission"
+            ));
+        } else if usize::try_from(quorum).is_ok_and(|quorum| quorum > discord_config.team.len()) {
+            problems.push(format!(
+                "`submissions.{key}` is `{quorum}`, which is more than the {team_size} members of `discord.team`",
+                team_size = discord_config.team.len()
+            ));
+        }
+    }
+}
+
+fn check_id(key: &str, id: u64, problems: &mut Vec<String>) {
+    let created = (id >> 22) + DISCORD_EPOCH;
+    let now = u64::try_from(chrono::Utc::now().timestamp_millis()).unwrap_or_default();
+
+    if id >> 22 == 0 {
+        problems.push(format!(
+            "`{key}` is `{id}`, which is too small to be a Discord ID"
+        ));
+    } else if created > now {
+        problems.push(format!(
+            "`{key}` is `{id}`, which would be a Discord ID from the future"
+        ));
+    }
+}
@@ -0,0 +1,416 @@
+use figment::{
+    providers::{Env, Format, Yaml},
+    Figment,
+};
+use figment_file_provider_adapter::FileAdapter;
+use serde::{Deserialize, Serialize};
+
+pub mod check;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Config {
+    pub mastodon: MastodonConfig,
+    #[serde(default)]
+    pub misskey: Option<MisskeyConfig>,
+    #[serde(default)]
+    pub bluesky: Option<BlueskyConfig>,
+    pub discord: DiscordConfig,
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub submissions: SubmissionsConfig,
+    #[serde(default)]
+    pub media: MediaConfig,
+    #[serde(default)]
+    pub feed: Option<FeedConfig>,
+    #[serde(default)]
+    pub server: Option<ServerConfig>,
+    /// How long posts and approvals that are in flight get to finish on
+    /// shutdown.
+    #[serde(
+        default = "default_shutdown_timeout_secs",
+        alias = "shutdown-timeout-secs"
+    )]
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Config {
+    pub fn load(path: &str) -> anyhow::Result<Self> {
+        let config = Self::figment(path).extract::<Self>()?;
+
+        // Submissions could never be decided with these, so the bot doesn't
+        // start with them rather than leaving it to `config check`.
+        let mut problems = vec![];
+        check::check_quorums(&config.discord, &config.submissions, &mut problems);
+        if !problems.is_empty() {
+            anyhow::bail!("{problems}", problems = problems.join(", "));
+        }
+
+        Ok(config)
+    }
+
+    fn figment(path: &str) -> Figment {
+        Figment::new()
+            .merge(FileAdapter::wrap(Yaml::file(path)).with_suffix("-file"))
+            .merge(FileAdapter::wrap(Env::raw().split("__")))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MastodonConfig {
+    #[serde(alias = "instance-host")]
+    pub instance_host: String,
+    #[serde(alias = "access-token")]
+    pub access_token: String,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+/// Also works for forks of Misskey, like Sharkey.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MisskeyConfig {
+    #[serde(alias = "instance-host")]
+    pub instance_host: String,
+    #[serde(alias = "access-token")]
+    pub access_token: String,
+    #[serde(default)]
+    pub visibility: MisskeyVisibility,
+    #[serde(default = "default_repost_visibility", alias = "repost-visibility")]
+    pub repost_visibility: MisskeyVisibility,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum MisskeyVisibility {
+    #[default]
+    Public,
+    Home,
+    Followers,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlueskyConfig {
+    #[serde(default = "default_bluesky_service")]
+    pub service: String,
+    /// Handle or DID of the account to post as.
+    pub identifier: String,
+    #[serde(alias = "app-password")]
+    pub app_password: String,
+    /// Self-label put on posts with a content warning, which the app hides
+    /// them behind. Set it to `null` to leave them unlabeled.
+    #[serde(
+        default = "default_bluesky_content_warning_label",
+        alias = "content-warning-label"
+    )]
+    pub content_warning_label: Option<BlueskyLabel>,
+    #[serde(default)]
+    pub retry: RetryConfig,
+}
+
+/// Labels Bluesky lets accounts put on their own posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlueskyLabel {
+    Sexual,
+    Nudity,
+    Porn,
+    GraphicMedia,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RetryConfig {
+    #[serde(default = "default_max_attempts", alias = "max-attempts")]
+    pub max_attempts: u32,
+    #[serde(default = "default_base_delay_ms", alias = "base-delay-ms")]
+    pub base_delay_ms: u64,
+    #[serde(default = "default_max_delay_secs", alias = "max-delay-secs")]
+    pub max_delay_secs: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscordConfig {
+    pub token: String,
+    #[serde(alias = "server-id")]
+    pub server_id: u64,
+    pub team: Vec<u64>,
+    /// Team members whose approval or rejection decides a submission right
+    /// away, whatever the quorum.
+    #[serde(default)]
+    pub admins: Vec<u64>,
+    pub channels: DiscordChannelConfig,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DiscordChannelConfig {
+    #[serde(alias = "approve-id")]
+    pub approve_id: u64,
+    #[serde(alias = "vote-id")]
+    pub vote_id: u64,
+    /// Moderation events are mirrored here as they happen.
+    #[serde(alias = "log-id")]
+    pub log_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionsConfig {
+    /// Keep approved submissions out of the posting queue until every one
+    /// of their images has alt text.
+    #[serde(default, alias = "require-alt-text")]
+    pub require_alt_text: bool,
+    /// Discord user that submissions imported from a backlog are attributed
+    /// to.
+    #[serde(alias = "system-user-id")]
+    pub system_user_id: Option<u64>,
+    /// Approvals a submission needs from the team before it's approved.
+    #[serde(default = "default_approval_quorum", alias = "approval-quorum")]
+    pub approval_quorum: u32,
+    /// Rejections a submission needs before it's rejected, any rejection
+    /// does by default.
+    #[serde(default = "default_rejection_quorum", alias = "rejection-quorum")]
+    pub rejection_quorum: u32,
+}
+
+/// Limits are named after the `media_attachments` configuration of the
+/// instance, which can be found at `/api/v2/instance`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MediaConfig {
+    #[serde(default = "default_storage_path", alias = "storage-path")]
+    pub storage_path: std::path::PathBuf,
+    #[serde(default = "default_image_size_limit", alias = "image-size-limit")]
+    pub image_size_limit: u64,
+    #[serde(default = "default_image_matrix_limit", alias = "image-matrix-limit")]
+    pub image_matrix_limit: u64,
+    #[serde(default = "default_video_size_limit", alias = "video-size-limit")]
+    pub video_size_limit: u64,
+    #[serde(
+        default = "default_supported_mime_types",
+        alias = "supported-mime-types"
+    )]
+    pub supported_mime_types: Vec<String>,
+}
+
+/// An Atom feed of published submissions, written as a static file that's
+/// served by something else.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedConfig {
+    pub path: std::path::PathBuf,
+    /// URL the feed is served at.
+    pub url: String,
+    #[serde(default = "default_feed_title")]
+    pub title: String,
+    #[serde(default = "default_feed_entries")]
+    pub entries: i64,
+    /// URL the media storage path is served at, for enclosures of the stored
+    /// images. Links to the submitted images are used without it.
+    #[serde(alias = "media-url")]
+    pub media_url: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ServerConfig {
+    #[serde(default = "default_server_address")]
+    pub address: std::net::SocketAddr,
+    /// How late a scheduled post can be before the scheduler is considered
+    /// stuck, which leaves room for retries.
+    #[serde(default = "default_tick_grace_secs", alias = "tick-grace-secs")]
+    pub tick_grace_secs: u64,
+    /// How long ago the last post can be, not checked if it's not set.
+    #[serde(alias = "max-post-age-secs")]
+    pub max_post_age_secs: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DatabaseConfig {
+    pub url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default)]
+    pub schedule: PostSchedule,
+    #[serde(default)]
+    pub timezone: chrono_tz::Tz,
+    #[serde(default)]
+    pub blackouts: Vec<BlackoutWindow>,
+    #[serde(default, alias = "empty-queue")]
+    pub empty_queue: EmptyQueueFallback,
+    #[serde(default = "default_repost_gap_days", alias = "repost-gap-days")]
+    pub repost_gap_days: i32,
+    #[serde(alias = "low-queue-threshold")]
+    pub low_queue_threshold: Option<i64>,
+    #[serde(default = "default_dead_letter_after", alias = "dead-letter-after")]
+    pub dead_letter_after: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PostSchedule {
+    /// Either a standard 5 field expression, which runs at the start of the
+    /// minute, or 6 to 7 fields with seconds first and an optional year last.
+    Cron(String),
+    Times(Vec<chrono::NaiveTime>),
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct BlackoutWindow {
+    pub start: chrono::NaiveTime,
+    pub end: chrono::NaiveTime,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EmptyQueueFallback {
+    #[default]
+    Skip,
+    Archive,
+    Status,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            schedule: PostSchedule::default(),
+            timezone: chrono_tz::Tz::default(),
+            blackouts: Vec::default(),
+            empty_queue: EmptyQueueFallback::default(),
+            repost_gap_days: default_repost_gap_days(),
+            low_queue_threshold: None,
+            dead_letter_after: default_dead_letter_after(),
+        }
+    }
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: default_max_attempts(),
+            base_delay_ms: default_base_delay_ms(),
+            max_delay_secs: default_max_delay_secs(),
+        }
+    }
+}
+
+impl Default for SubmissionsConfig {
+    fn default() -> Self {
+        Self {
+            require_alt_text: false,
+            system_user_id: None,
+            approval_quorum: default_approval_quorum(),
+            rejection_quorum: default_rejection_quorum(),
+        }
+    }
+}
+
+impl Default for MediaConfig {
+    fn default() -> Self {
+        Self {
+            storage_path: default_storage_path(),
+            image_size_limit: default_image_size_limit(),
+            image_matrix_limit: default_image_matrix_limit(),
+            video_size_limit: default_video_size_limit(),
+            supported_mime_types: default_supported_mime_types(),
+        }
+    }
+}
+
+impl Default for PostSchedule {
+    fn default() -> Self {
+        Self::Cron(String::from("0 0 * * * *"))
+    }
+}
+
+const fn default_repost_gap_days() -> i32 {
+    30
+}
+
+const fn default_dead_letter_after() -> i64 {
+    3
+}
+
+const fn default_max_attempts() -> u32 {
+    5
+}
+
+const fn default_base_delay_ms() -> u64 {
+    500
+}
+
+const fn default_max_delay_secs() -> u64 {
+    60
+}
+
+const fn default_repost_visibility() -> MisskeyVisibility {
+    MisskeyVisibility::Home
+}
+
+fn default_bluesky_service() -> String {
+    String::from("https://bsky.social")
+}
+
+const fn default_bluesky_content_warning_label() -> Option<BlueskyLabel> {
+    Some(BlueskyLabel::GraphicMedia)
+}
+
+fn default_storage_path() -> std::path::PathBuf {
+    dirs::data_dir()
+        .expect("unsupported operating system or platform")
+        .join("YuriEveryDay_bot")
+        .join("media")
+}
+
+const fn default_approval_quorum() -> u32 {
+    1
+}
+
+const fn default_rejection_quorum() -> u32 {
+    1
+}
+
+const fn default_image_size_limit() -> u64 {
+    16 * 1024 * 1024
+}
+
+const fn default_image_matrix_limit() -> u64 {
+    33_177_600
+}
+
+const fn default_video_size_limit() -> u64 {
+    99 * 1024 * 1024
+}
+
+fn default_supported_mime_types() -> Vec<String> {
+    [
+        "image/jpeg",
+        "image/png",
+        "image/gif",
+        "image/webp",
+        "video/webm",
+        "video/mp4",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+fn default_feed_title() -> String {
+    String::from("Yuri Every Hour")
+}
+
+const fn default_feed_entries() -> i64 {
+    20
+}
+
+fn default_server_address() -> std::net::SocketAddr {
+    std::net::SocketAddr::from(([0, 0, 0, 0], 8080))
+}
+
+const fn default_tick_grace_secs() -> u64 {
+    15 * 60
+}
+
+const fn default_shutdown_timeout_secs() -> u64 {
+    25
+}
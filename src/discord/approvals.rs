@@ -0,0 +1,255 @@
+use serenity::all::UserId;
+use sqlx::{PgExecutor, PgPool};
+
+use crate::{
+    discord::{data::YuriData, YuriConfig},
+    models::{
+        audit_log::{AddAuditEntry, AuditAction, AuditEntry, AuditEntryHelpers},
+        images::{Image, ImageHelpers},
+        pending_approvals::{PendingApproval, PendingApprovalHelpers, RemovePendingApproval},
+        reviewer_votes::{AddReviewerVote, ReviewerVote, ReviewerVoteHelpers},
+        submissions::{Submission, SubmissionDecision, SubmissionHelpers, SubmissionIds},
+    },
+};
+
+/// What came of a reviewer clicking **Approve** or **Reject**.
+pub enum ApprovalOutcome {
+    /// Nothing was decided, only the reviewer is told why.
+    Refused(String),
+    Expired {
+        submission_id: i32,
+    },
+    /// The reviewer's vote was counted, but more are needed to decide.
+    Voted(Box<ApprovalUpdate>),
+    Decided {
+        approval_update: Box<ApprovalUpdate>,
+        decision: SubmissionDecision,
+        /// An admin decided before the quorum was reached.
+        overridden: bool,
+    },
+}
+
+/// What the approval message is rebuilt from after a vote.
+pub struct ApprovalUpdate {
+    pub submission: Submission,
+    pub images: Vec<Image>,
+    pub votes: Vec<ReviewerVote>,
+}
+
+/// Counts the reviewer's vote on the submission of an approval message, as
+/// long as they're allowed to and it wasn't decided on yet. The submission is
+/// decided once its quorum is reached, or right away when the reviewer is an
+/// admin.
+pub async fn decide_approval(
+    database: &PgPool,
+    data: &YuriData,
+    config: &YuriConfig,
+    message_id: i64,
+    reviewer_id: UserId,
+    decision: SubmissionDecision,
+) -> anyhow::Result<ApprovalOutcome> {
+    // Held until the vote is counted, so a submission is only handled once at
+    // a time while the others can be handled meanwhile.
+    let Some(pending_approval) = data.lock_pending_approval(message_id).await else {
+        return Ok(ApprovalOutcome::Refused(String::from(
+            "This approval does not exist.",
+        )));
+    };
+
+    if chrono::Utc::now()
+        .naive_utc()
+        .signed_duration_since(pending_approval.date)
+        .num_days()
+        >= 1
+    {
+        let mut tx = database.begin().await?;
+        if PendingApproval::remove_pending_approval(
+            &mut *tx,
+            RemovePendingApproval::MessageId(u64::try_from(message_id)?),
+        )
+        .await?
+        .is_some()
+        {
+            add_expiry_entry(&mut *tx, pending_approval.submission_id).await?;
+        }
+        tx.commit().await?;
+        data.forget_pending_approval(pending_approval.message_id);
+        metrics::counter!("yuri_expired_approvals_total").increment(1);
+
+        return Ok(ApprovalOutcome::Expired {
+            submission_id: pending_approval.submission_id,
+        });
+    }
+
+    if !config.team.contains(&reviewer_id) {
+        return Ok(ApprovalOutcome::Refused(String::from(
+            "You don't have enough permissions to do that.",
+        )));
+    }
+
+    // Decided approvals are kept around until they're populated again, so
+    // whoever clicks late is told who decided.
+    let submission = Submission::get_submission(
+        database,
+        SubmissionIds::SubmissionId(pending_approval.submission_id),
+    )
+    .await?;
+    if submission.decision.is_some() {
+        return Ok(ApprovalOutcome::Refused(
+            already_decided_reason(database, submission.submission_id).await?,
+        ));
+    }
+
+    let images = Image::get_submission_images(database, submission.submission_id).await?;
+    if matches!(decision, SubmissionDecision::Approved) && images.is_empty() {
+        return Ok(ApprovalOutcome::Refused(String::from(
+            "This submission doesn't have any images to post, so it can't be approved.",
+        )));
+    }
+
+    if matches!(decision, SubmissionDecision::Approved)
+        && config.submissions.require_alt_text
+        && images.iter().any(|image| image.alt_text.is_none())
+    {
+        return Ok(ApprovalOutcome::Refused(String::from(
+            "Every image of this submission needs alt text before it can be approved, add it with **Edit Alt Text**.",
+        )));
+    }
+
+    let (audit_action, decision_value, quorum) = match decision {
+        SubmissionDecision::Approved => (
+            AuditAction::Approve,
+            "approved",
+            config.submissions.approval_quorum,
+        ),
+        SubmissionDecision::Rejected => (
+            AuditAction::Reject,
+            "rejected",
+            config.submissions.rejection_quorum,
+        ),
+    };
+
+    let mut tx = database.begin().await?;
+    ReviewerVote::add_reviewer_vote(
+        &mut *tx,
+        AddReviewerVote {
+            submission_id: submission.submission_id,
+            reviewer_id: reviewer_id.get(),
+            approve: matches!(decision, SubmissionDecision::Approved),
+        },
+    )
+    .await?;
+    let votes = ReviewerVote::get_reviewer_votes(&mut *tx, submission.submission_id).await?;
+    metrics::counter!("yuri_reviewer_votes_total", "vote" => decision_value).increment(1);
+
+    let quorum_reached = votes
+        .iter()
+        .filter(|vote| vote.approve == matches!(decision, SubmissionDecision::Approved))
+        .count()
+        >= quorum as usize;
+    let overridden = !quorum_reached && config.admins.contains(&reviewer_id);
+    if !quorum_reached && !overridden {
+        tx.commit().await?;
+
+        return Ok(ApprovalOutcome::Voted(Box::new(ApprovalUpdate {
+            submission,
+            images,
+            votes,
+        })));
+    }
+
+    let decided_submission = match decision {
+        SubmissionDecision::Approved => {
+            Submission::approve_submission(
+                &mut *tx,
+                SubmissionIds::SubmissionId(submission.submission_id),
+            )
+            .await?
+        }
+        SubmissionDecision::Rejected => {
+            Submission::reject_submission(
+                &mut *tx,
+                SubmissionIds::SubmissionId(submission.submission_id),
+            )
+            .await?
+        }
+    };
+    let Some(decided_submission) = decided_submission else {
+        return Ok(ApprovalOutcome::Refused(
+            already_decided_reason(database, submission.submission_id).await?,
+        ));
+    };
+
+    AuditEntry::add_audit_entry(
+        &mut *tx,
+        AddAuditEntry {
+            actor_id: Some(reviewer_id.get()),
+            action: audit_action,
+            submission_id: Some(decided_submission.submission_id),
+            before_value: Some(String::from("pending")),
+            after_value: Some(String::from(decision_value)),
+        },
+    )
+    .await?;
+    PendingApproval::remove_pending_approval(
+        &mut *tx,
+        RemovePendingApproval::SubmissionId(decided_submission.submission_id),
+    )
+    .await?;
+    tx.commit().await?;
+    metrics::counter!("yuri_submission_decisions_total", "decision" => decision_value).increment(1);
+
+    Ok(ApprovalOutcome::Decided {
+        approval_update: Box::new(ApprovalUpdate {
+            submission: decided_submission,
+            images,
+            votes,
+        }),
+        decision,
+        overridden,
+    })
+}
+
+/// Tells who decided on a submission before the reviewer, whatever was
+/// decided first stands.
+pub async fn already_decided_reason(
+    database: &PgPool,
+    submission_id: i32,
+) -> anyhow::Result<String> {
+    let submission =
+        Submission::get_submission(database, SubmissionIds::SubmissionId(submission_id)).await?;
+    let decision = match submission.decision {
+        Some(SubmissionDecision::Approved) => "approved",
+        Some(SubmissionDecision::Rejected) => "rejected",
+        None => "decided on",
+    };
+    let decider = AuditEntry::get_decision_entry(database, submission_id)
+        .await?
+        .and_then(|decision_entry| decision_entry.actor_id)
+        .map_or_else(
+            || String::from("Someone else"),
+            |actor_id| format!("<@{actor_id}>"),
+        );
+
+    Ok(format!("{decider} already {decision} this submission."))
+}
+
+/// Approvals expire on their own, so the entry has no actor.
+pub async fn add_expiry_entry(
+    executor: impl PgExecutor<'_>,
+    submission_id: i32,
+) -> anyhow::Result<()> {
+    AuditEntry::add_audit_entry(
+        executor,
+        AddAuditEntry {
+            actor_id: None,
+            action: AuditAction::Expire,
+            submission_id: Some(submission_id),
+            before_value: Some(String::from("pending")),
+            after_value: Some(String::from("expired")),
+        },
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,28 +1,53 @@
+use std::{collections::HashSet, sync::Arc};
+
+use dashmap::DashMap;
 use sqlx::PgExecutor;
+use tokio::sync::{Mutex, OwnedMutexGuard};
 
 use crate::models::pending_approvals::{
-    AddPendingApproval, PendingApproval, PendingApprovalHelpers, RemovePendingApproval,
+    AddPendingApproval, PendingApproval, PendingApprovalHelpers,
 };
 
+/// Pending approvals keyed by the ID of their message. Each one has a lock of
+/// its own, so handling one submission never holds up the others. Decided
+/// approvals stay until the next time they're populated, so reviewers who
+/// click late are told who decided rather than that there's no approval.
+#[derive(Default)]
 pub struct YuriData {
-    pub pending_approvals: Vec<PendingApproval>,
+    pending_approvals: DashMap<i64, Arc<Mutex<PendingApproval>>>,
+}
+
+impl YuriData {
+    /// Waits for whoever is handling the approval to be done with it. Gives
+    /// [`None`] if there's no approval for the message. The approval may have
+    /// been decided on while waiting, which is up to the database to tell.
+    pub async fn lock_pending_approval(
+        &self,
+        message_id: i64,
+    ) -> Option<OwnedMutexGuard<PendingApproval>> {
+        // The map's guard can't be held across an await, so the approval's
+        // lock is taken after it's dropped.
+        let pending_approval = self.pending_approvals.get(&message_id)?.clone();
+
+        Some(pending_approval.lock_owned().await)
+    }
+
+    /// Drops an approval that's already gone from the database. Has to be
+    /// called while holding the approval's lock, once the transaction that
+    /// removed it is committed.
+    pub fn forget_pending_approval(&self, message_id: i64) {
+        self.pending_approvals.remove(&message_id);
+    }
 }
 
 pub trait PendingApprovalsHelpers {
     async fn add_pending_approval(
-        &mut self,
+        &self,
         executor: impl PgExecutor,
         add_pending_approval: AddPendingApproval,
     ) -> anyhow::Result<()>;
 
-    async fn remove_pending_approval(
-        &mut self,
-        executor: impl PgExecutor,
-        remove_pending_approval: RemovePendingApproval,
-    ) -> anyhow::Result<()>;
-
-    async fn populate_pending_approvals(&mut self, executor: impl PgExecutor)
-        -> anyhow::Result<()>;
+    async fn populate_pending_approvals(&self, executor: impl PgExecutor) -> anyhow::Result<()>;
 
     // async fn depopulate_expired_approvals(
     //     &mut self,
@@ -32,32 +57,22 @@ pub trait PendingApprovalsHelpers {
 
 impl PendingApprovalsHelpers for YuriData {
     async fn add_pending_approval(
-        &mut self,
+        &self,
         executor: impl PgExecutor<'_>,
         add_pending_approval: AddPendingApproval,
     ) -> anyhow::Result<()> {
         let added_approval =
             PendingApproval::add_pending_approval(executor, add_pending_approval).await?;
-        self.pending_approvals.push(added_approval);
-
-        Ok(())
-    }
-
-    async fn remove_pending_approval(
-        &mut self,
-        executor: impl PgExecutor<'_>,
-        remove_approval: RemovePendingApproval,
-    ) -> anyhow::Result<()> {
-        let removed_approval =
-            PendingApproval::remove_pending_approval(executor, remove_approval).await?;
-        self.pending_approvals
-            .retain(|pending_approval| *pending_approval != removed_approval);
+        self.pending_approvals.insert(
+            added_approval.message_id,
+            Arc::new(Mutex::new(added_approval)),
+        );
 
         Ok(())
     }
 
     async fn populate_pending_approvals(
-        &mut self,
+        &self,
         executor: impl PgExecutor<'_>,
     ) -> anyhow::Result<()> {
         debug!("populating pending approvals");
@@ -68,10 +83,22 @@ impl PendingApprovalsHelpers for YuriData {
 
         if pending_approvals.is_empty() {
             debug!("no pending approvals found to populate");
-            return Ok(());
         }
 
-        self.pending_approvals.extend(pending_approvals);
+        // `ready` fires again when the bot reconnects with a new session, so
+        // approvals that are gone by then are dropped. The ones that are
+        // still there keep their lock, since they may be handled right now.
+        let message_ids = pending_approvals
+            .iter()
+            .map(|pending_approval| pending_approval.message_id)
+            .collect::<HashSet<_>>();
+        for pending_approval in pending_approvals {
+            self.pending_approvals
+                .entry(pending_approval.message_id)
+                .or_insert_with(|| Arc::new(Mutex::new(pending_approval)));
+        }
+        self.pending_approvals
+            .retain(|message_id, _| message_ids.contains(message_id));
 
         debug!("populated pending approvals");
         Ok(())
@@ -1,15 +1,17 @@
-use std::sync::Arc;
+use std::sync::{atomic::Ordering, Arc};
 
-use serenity::all::Interaction;
+use serenity::all::{
+    ChannelId, ConnectionStage, CreateInteractionResponse, CreateInteractionResponseMessage,
+    Interaction, MessageId, ShardStageUpdateEvent,
+};
 use serenity::client::{Context, EventHandler};
 use serenity::model::gateway::Ready;
 
 use crate::discord::data::PendingApprovalsHelpers;
 use crate::discord::interactions::register_interactions;
-use crate::models::pending_approvals::{PendingApproval, PendingApprovalHelpers};
 
 use super::interactions::run_interactions;
-use super::{YuriDiscord, YuriState};
+use super::{ModerationEvent, YuriDiscord, YuriState};
 
 pub struct Handler {
     pub state: Arc<YuriState>,
@@ -22,27 +24,94 @@ impl EventHandler for Handler {
             "Connected to Discord as {username}",
             username = ready.user.name
         );
+        self.state
+            .gateway_status
+            .connected
+            .store(true, Ordering::Relaxed);
 
         register_interactions(self.state.config.server_id, context).await;
 
-        let yuri_data = &mut self.state.data.lock().await;
-        {
-            if let Err(error) =
-                PendingApproval::remove_expired_approvals(&self.state.database).await
-            {
+        match self.remove_expired_approvals().await {
+            Ok(Some(expired_approvals)) => {
+                metrics::counter!("yuri_expired_approvals_total")
+                    .increment(expired_approvals.len() as u64);
+
+                for expired_approval in expired_approvals {
+                    let Ok(message_id) = u64::try_from(expired_approval.message_id) else {
+                        continue;
+                    };
+
+                    self.state
+                        .moderation_log
+                        .log(ModerationEvent::Expired {
+                            submission_id: expired_approval.submission_id,
+                            channel_id: ChannelId::new(self.state.config.channels.approve_id),
+                            message_id: MessageId::new(message_id),
+                        })
+                        .await;
+                }
+            }
+            Ok(None) => {}
+            Err(error) => {
                 error!("an error occurred while removing expired approvals: {error:#?}");
             }
+        }
 
-            if let Err(error) = yuri_data
-                .populate_pending_approvals(&self.state.database)
-                .await
-            {
-                error!("an error occurred while populating pending approvals: {error:#?}");
-            };
+        if let Err(error) = self.reconcile_pending_approvals(context).await {
+            error!("an error occurred while reconciling pending approvals: {error:#?}");
         }
+
+        if let Err(error) = self
+            .state
+            .data
+            .populate_pending_approvals(&self.state.database)
+            .await
+        {
+            error!("an error occurred while populating pending approvals: {error:#?}");
+        };
+    }
+
+    async fn shard_stage_update(&self, _context: &Context, event: &ShardStageUpdateEvent) {
+        debug!(
+            "shard connection stage changed from {old:?} to {new:?}",
+            old = event.old,
+            new = event.new
+        );
+
+        self.state
+            .gateway_status
+            .connected
+            .store(event.new == ConnectionStage::Connected, Ordering::Relaxed);
     }
 
     async fn interaction_create(&self, context: &Context, interaction: &Interaction) {
+        // Taken before checking for a shutdown, see `Shutdown::track`.
+        let _in_flight = self.state.shutdown.track();
+        if self.state.shutdown.is_triggered() {
+            let response = CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content("The bot is restarting, try again in a minute!")
+                    .ephemeral(true),
+            );
+
+            let result = match interaction {
+                Interaction::Command(command) => {
+                    command.create_response(&context.http, response).await
+                }
+                Interaction::Component(component_interaction) => {
+                    component_interaction
+                        .create_response(&context.http, response)
+                        .await
+                }
+                _ => Ok(()),
+            };
+            if let Err(error) = result {
+                error!("an error occurred while turning away an interaction: {error:#?}");
+            }
+
+            return;
+        }
+
         match interaction {
             Interaction::Command(command) => {
                 if let Err(error) = run_interactions(
@@ -58,11 +127,41 @@ impl EventHandler for Handler {
                         "an error occurred while running `{interaction_name}` interaction: {error:#?}",
                         interaction_name = command.data.name
                     );
+                    metrics::counter!(
+                        "yuri_interaction_errors_total",
+                        "interaction" => command.data.name.to_string()
+                    )
+                    .increment(1);
                 }
             }
             Interaction::Component(component_interaction) => {
-                if let Err(error) = &self.handle_approvals(component_interaction, context).await {
-                    error!("an error occurred while handling approvals: {error:#?}");
+                // Modals are collected by the handler of the component that
+                // opened them, so their errors end up here too.
+                let custom_id = component_interaction.data.custom_id.as_str();
+                let (result, handled) = match custom_id {
+                    "edit_alt_text" => (
+                        self.handle_alt_text_edits(component_interaction, context)
+                            .await,
+                        "alt text edits",
+                    ),
+                    "edit_images" => (
+                        self.handle_image_edits(component_interaction, context)
+                            .await,
+                        "image edits",
+                    ),
+                    _ => (
+                        self.handle_approvals(component_interaction, context).await,
+                        "approvals",
+                    ),
+                };
+
+                if let Err(error) = result {
+                    error!("an error occurred while handling {handled}: {error:#?}");
+                    metrics::counter!(
+                        "yuri_interaction_errors_total",
+                        "interaction" => custom_id.to_string()
+                    )
+                    .increment(1);
                 }
             }
             _ => {}
@@ -1,18 +1,34 @@
+use std::time::Duration;
+
 use serenity::all::{
-    CacheHttp, Colour, ComponentInteraction, Context, CreateEmbed, CreateInteractionResponse,
-    CreateInteractionResponseMessage, EditMessage,
+    ChannelId, Colour, ComponentInteraction, Context, CreateEmbed, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    EditInteractionResponse, InputTextStyle, MessageId, ModalInteraction, Timestamp, UserId,
 };
+use serenity::utils::CreateQuickModal;
+use tokio::sync::OwnedMutexGuard;
 
 use crate::{
-    discord::data::PendingApprovalsHelpers,
+    discord::{
+        approvals::{add_expiry_entry, already_decided_reason, decide_approval, ApprovalOutcome},
+        interactions::yuri::{alt_text_input, alt_text_label, approval_message, submission_embeds},
+        ModerationEvent,
+    },
     models::{
-        pending_approvals::RemovePendingApproval,
-        submissions::{Submission, SubmissionHelpers, SubmissionIds},
+        audit_log::{AddAuditEntry, AuditAction, AuditEntry, AuditEntryHelpers},
+        images::{Image, ImageHelpers},
+        pending_approvals::{PendingApproval, PendingApprovalHelpers},
+        reviewer_votes::{ReviewerVote, ReviewerVoteHelpers},
+        submissions::{Submission, SubmissionDecision, SubmissionHelpers, SubmissionIds},
     },
 };
 
 use super::event_handler::Handler;
 
+/// Discord shows an interaction as failed if it isn't responded to within
+/// three seconds, this leaves some room for the response itself.
+const RESPONSE_DEADLINE: Duration = Duration::from_millis(2500);
+
 impl Handler {
     pub async fn handle_approvals(
         &self,
@@ -21,150 +37,747 @@ impl Handler {
     ) -> anyhow::Result<()> {
         debug!("handling an approval");
 
-        let yuri_data = &mut self.state.data.lock().await;
-
-        let mut pending_approval = None;
-        for approval in &yuri_data.pending_approvals {
-            if approval.message_id == i64::try_from(interaction.message.id.get())? {
-                pending_approval = Some(approval.clone());
-                break;
-            }
-        }
+        // The approval message is updated as the response when deciding is
+        // quick enough, otherwise the response is deferred and the message is
+        // updated once it's decided.
+        let approve = interaction.data.custom_id.as_str() == "approve";
+        let decision = if approve {
+            SubmissionDecision::Approved
+        } else {
+            SubmissionDecision::Rejected
+        };
+        let approval_outcome = decide_approval(
+            &self.state.database,
+            &self.state.data,
+            &self.state.config,
+            i64::try_from(interaction.message.id.get())?,
+            interaction.user.id,
+            decision,
+        );
+        tokio::pin!(approval_outcome);
+        let (approval_outcome, deferred) =
+            match tokio::time::timeout(RESPONSE_DEADLINE, &mut approval_outcome).await {
+                Ok(approval_outcome) => (approval_outcome?, false),
+                Err(_) => {
+                    debug!("deciding on an approval is taking a while, deferring the response");
+                    interaction
+                        .create_response(&context.http, CreateInteractionResponse::Acknowledge)
+                        .await?;
 
-        if let Some(pending_approval) = pending_approval {
-            if chrono::Utc::now()
-                .naive_utc()
-                .signed_duration_since(pending_approval.date)
-                .num_days()
-                >= 1
-            {
-                yuri_data
-                    .remove_pending_approval(
-                        &self.state.database,
-                        RemovePendingApproval::MessageId(interaction.message.id.get()),
-                    )
-                    .await?;
-
-                interaction
-                    .create_response(
-                        &context.http,
-                        CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::new()
-                                .content("This approval has been expired and removed.")
-                                .ephemeral(true),
-                        ),
-                    )
-                    .await?;
+                    (approval_outcome.await?, true)
+                }
+            };
 
-                return Ok(());
+        match approval_outcome {
+            ApprovalOutcome::Refused(reason) => {
+                respond_ephemeral(interaction, context, deferred, reason).await?;
             }
-
-            let mut pending_approval_message = context
-                .http()
-                .get_message(
-                    interaction.channel_id,
-                    (u64::try_from(pending_approval.message_id)?).into(),
+            ApprovalOutcome::Expired { submission_id } => {
+                respond_ephemeral(
+                    interaction,
+                    context,
+                    deferred,
+                    String::from("This approval has been expired and removed."),
                 )
                 .await?;
 
-            if self.state.config.team.contains(&interaction.user.id) {
-                let embed = interaction
-                    .message
-                    .embeds
-                    .first()
-                    .map(|embed| CreateEmbed::from(embed.clone()))
-                    .expect("that message supposed to have an embed, but here we are.");
+                self.state
+                    .moderation_log
+                    .log(ModerationEvent::Expired {
+                        submission_id,
+                        channel_id: interaction.channel_id,
+                        message_id: interaction.message.id,
+                    })
+                    .await;
+            }
+            ApprovalOutcome::Voted(approval_update) => {
+                let embeds = approval_embeds(
+                    interaction,
+                    &approval_update.submission,
+                    &approval_update.images,
+                    &approval_update.votes,
+                );
 
-                if &interaction.data.custom_id.to_string() == "approve" {
-                    let mut tx = self.state.database.begin().await?;
-                    Submission::approve_submission(
-                        &mut *tx,
-                        SubmissionIds::SubmissionId(pending_approval.submission_id),
-                    )
-                    .await?;
-                    yuri_data
-                        .remove_pending_approval(
-                            &mut *tx,
-                            RemovePendingApproval::SubmissionId(pending_approval.submission_id),
-                        )
+                // The buttons are kept, so the rest of the team can still
+                // vote.
+                if deferred {
+                    interaction
+                        .edit_response(&context.http, EditInteractionResponse::new().embeds(embeds))
                         .await?;
-                    tx.commit().await?;
-
-                    pending_approval_message
-                        .edit(
-                            context,
-                            EditMessage::new()
-                                .embed(
-                                    embed
-                                        .title(format!(
-                                            "Approved by {user_tag}!",
-                                            user_tag = interaction.user.tag()
-                                        ))
-                                        .colour(Colour::DARK_GREEN),
-                                )
-                                .components(vec![]),
+                } else {
+                    interaction
+                        .create_response(
+                            &context.http,
+                            CreateInteractionResponse::UpdateMessage(
+                                CreateInteractionResponseMessage::new().embeds(embeds),
+                            ),
                         )
                         .await?;
-                } else {
-                    let mut tx = self.state.database.begin().await?;
-                    Submission::reject_submission(
-                        &mut *tx,
-                        SubmissionIds::SubmissionId(pending_approval.submission_id),
+                }
+
+                self.state
+                    .moderation_log
+                    .log(ModerationEvent::Voted {
+                        submission_id: approval_update.submission.submission_id,
+                        reviewer_id: interaction.user.id,
+                        approve,
+                        votes: approval_update
+                            .votes
+                            .iter()
+                            .filter(|vote| vote.approve == approve)
+                            .count(),
+                        quorum: if approve {
+                            self.state.config.submissions.approval_quorum
+                        } else {
+                            self.state.config.submissions.rejection_quorum
+                        },
+                        channel_id: interaction.channel_id,
+                        message_id: interaction.message.id,
+                    })
+                    .await;
+            }
+            ApprovalOutcome::Decided {
+                approval_update,
+                decision,
+                overridden,
+            } => {
+                let submission_id = approval_update.submission.submission_id;
+                let reviewer = if overridden {
+                    format!(
+                        "{user_tag} (admin override)",
+                        user_tag = interaction.user.tag()
                     )
-                    .await?;
-                    yuri_data
-                        .remove_pending_approval(
-                            &mut *tx,
-                            RemovePendingApproval::SubmissionId(pending_approval.submission_id),
+                } else {
+                    interaction.user.tag()
+                };
+                let (title, colour, moderation_event) = match decision {
+                    SubmissionDecision::Approved => (
+                        format!("Approved by {reviewer}!"),
+                        Colour::DARK_GREEN,
+                        ModerationEvent::Approved {
+                            submission_id,
+                            reviewer_id: interaction.user.id,
+                            channel_id: interaction.channel_id,
+                            message_id: interaction.message.id,
+                        },
+                    ),
+                    SubmissionDecision::Rejected => (
+                        format!("Rejected by {reviewer}!"),
+                        Colour::RED,
+                        ModerationEvent::Rejected {
+                            submission_id,
+                            reviewer_id: interaction.user.id,
+                            channel_id: interaction.channel_id,
+                            message_id: interaction.message.id,
+                        },
+                    ),
+                };
+
+                let mut embeds = approval_embeds(
+                    interaction,
+                    &approval_update.submission,
+                    &approval_update.images,
+                    &approval_update.votes,
+                )
+                .into_iter();
+                let embed = embeds
+                    .next()
+                    .expect("that message supposed to have an embed, but here we are.");
+                // The image gallery is made of the embeds after the first one.
+                let embeds = std::iter::once(embed.title(title).colour(colour))
+                    .chain(embeds)
+                    .collect::<Vec<_>>();
+
+                if deferred {
+                    interaction
+                        .edit_response(
+                            &context.http,
+                            EditInteractionResponse::new()
+                                .embeds(embeds)
+                                .components(vec![]),
                         )
                         .await?;
-                    tx.commit().await?;
-
-                    pending_approval_message
-                        .edit(
-                            context,
-                            EditMessage::new()
-                                .embed(
-                                    embed
-                                        .title(format!(
-                                            "Rejected by {user_tag}!",
-                                            user_tag = interaction.user.tag()
-                                        ))
-                                        .colour(Colour::RED),
-                                )
-                                .components(vec![]),
+                } else {
+                    interaction
+                        .create_response(
+                            &context.http,
+                            CreateInteractionResponse::UpdateMessage(
+                                CreateInteractionResponseMessage::new()
+                                    .embeds(embeds)
+                                    .components(vec![]),
+                            ),
                         )
                         .await?;
                 }
-            } else {
-                interaction
-                    .create_response(
-                        &context.http,
-                        CreateInteractionResponse::Message(
-                            CreateInteractionResponseMessage::new()
-                                .content("You don't have enough permissions to do that.")
-                                .ephemeral(true),
-                        ),
-                    )
-                    .await?;
+
+                self.state.moderation_log.log(moderation_event).await;
+
+                // In case storing them when it was submitted failed.
+                if matches!(decision, SubmissionDecision::Approved) {
+                    if let Err(error) = self
+                        .state
+                        .media
+                        .store_originals(&self.state.database, submission_id)
+                        .await
+                    {
+                        error!(
+                            "an error occurred while storing the originals of a submission with `submission_id`: {submission_id}: {error:#?}"
+                        );
+                    }
+                }
             }
-        } else {
+        }
+
+        debug!(
+            "handled an approval with `message_id`: {message_id}",
+            message_id = interaction.message.id
+        );
+        Ok(())
+    }
+
+    /// Removes approvals that went without a decision for too long.
+    pub async fn remove_expired_approvals(&self) -> anyhow::Result<Option<Vec<PendingApproval>>> {
+        let mut tx = self.state.database.begin().await?;
+        let expired_approvals = PendingApproval::remove_expired_approvals(&mut *tx).await?;
+        for expired_approval in expired_approvals.iter().flatten() {
+            add_expiry_entry(&mut *tx, expired_approval.submission_id).await?;
+        }
+        tx.commit().await?;
+
+        Ok(expired_approvals)
+    }
+
+    pub async fn handle_alt_text_edits(
+        &self,
+        interaction: &ComponentInteraction,
+        context: &Context,
+    ) -> anyhow::Result<()> {
+        debug!("handling an alt text edit");
+
+        let Some(pending_approval) = self.get_editable_approval(interaction, context).await? else {
+            return Ok(());
+        };
+
+        let images =
+            Image::get_submission_images(&self.state.database, pending_approval.submission_id)
+                .await?;
+        if images.is_empty() {
             interaction
                 .create_response(
                     &context.http,
                     CreateInteractionResponse::Message(
                         CreateInteractionResponseMessage::new()
-                            .content("This approval does not exist.")
+                            .content("This submission doesn't have any images to describe.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        // Every image is described on its own, there's room for all of them
+        // in a modal.
+        let alt_text_modal = images.iter().enumerate().fold(
+            CreateQuickModal::new("Edit Alt Text"),
+            |alt_text_modal, (index, image)| {
+                let alt_text_input = alt_text_input(alt_text_label(index, images.len()));
+                alt_text_modal.field(match &image.alt_text {
+                    Some(alt_text) => alt_text_input.value(alt_text.clone()),
+                    None => alt_text_input,
+                })
+            },
+        );
+        let Some(modal_response) = interaction.quick_modal(context, alt_text_modal).await? else {
+            return Ok(());
+        };
+
+        let alt_texts = modal_response
+            .inputs
+            .iter()
+            .map(|input| Some(input.trim().to_string()).filter(|alt_text| !alt_text.is_empty()))
+            .collect::<Vec<_>>();
+
+        let Some((_pending_approval, submission)) = self
+            .relock_editable_approval(&modal_response.interaction, context, &pending_approval)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        // The descriptions only go with the images they were written for.
+        let current_images =
+            Image::get_submission_images(&self.state.database, submission.submission_id).await?;
+        if current_images
+            .iter()
+            .map(|image| image.image_id)
+            .ne(images.iter().map(|image| image.image_id))
+        {
+            modal_response
+                .interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("The images of this submission were changed meanwhile, edit the alt text again.")
                             .ephemeral(true),
                     ),
                 )
                 .await?;
+
+            return Ok(());
         }
 
+        let mut tx = self.state.database.begin().await?;
+        let mut images = Vec::with_capacity(current_images.len());
+        for (image, alt_text) in current_images.into_iter().zip(alt_texts) {
+            if image.alt_text == alt_text {
+                images.push(image);
+                continue;
+            }
+
+            let updated_image = Image::update_alt_text(&mut *tx, image.image_id, alt_text).await?;
+            AuditEntry::add_audit_entry(
+                &mut *tx,
+                AddAuditEntry {
+                    actor_id: Some(interaction.user.id.get()),
+                    action: AuditAction::EditAltText,
+                    submission_id: Some(submission.submission_id),
+                    before_value: image.alt_text,
+                    after_value: updated_image.alt_text.clone(),
+                },
+            )
+            .await?;
+            images.push(updated_image);
+        }
+        tx.commit().await?;
+
+        let votes =
+            ReviewerVote::get_reviewer_votes(&self.state.database, submission.submission_id)
+                .await?;
+
+        modal_response
+            .interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().embeds(approval_embeds(
+                        interaction,
+                        &submission,
+                        &images,
+                        &votes,
+                    )),
+                ),
+            )
+            .await?;
+
         debug!(
-            "handled an approval with `message_id`: {message_id}",
+            "handled an alt text edit with `message_id`: {message_id}",
             message_id = interaction.message.id
         );
         Ok(())
     }
+
+    pub async fn handle_image_edits(
+        &self,
+        interaction: &ComponentInteraction,
+        context: &Context,
+    ) -> anyhow::Result<()> {
+        debug!("handling an image edit");
+
+        let Some(pending_approval) = self.get_editable_approval(interaction, context).await? else {
+            return Ok(());
+        };
+
+        let images =
+            Image::get_submission_images(&self.state.database, pending_approval.submission_id)
+                .await?;
+        if images.is_empty() {
+            interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("This submission doesn't have any images.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let current_order = (1..=images.len())
+            .map(|number| number.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let Some(modal_response) = interaction
+            .quick_modal(
+                context,
+                CreateQuickModal::new("Reorder or Drop Images").field(
+                    CreateInputText::new(InputTextStyle::Short, "Image Order", "")
+                        .placeholder("Leave out the numbers of images to drop")
+                        .value(current_order),
+                ),
+            )
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let Some((_pending_approval, submission)) = self
+            .relock_editable_approval(&modal_response.interaction, context, &pending_approval)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let Some(image_ids) = parse_image_order(&modal_response.inputs[0], &images) else {
+            modal_response
+                .interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content(format!(
+                                "That isn't a valid image order, use each number from 1 to {count} at most once, separated by commas, and keep at least one image.",
+                                count = images.len()
+                            ))
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+
+            return Ok(());
+        };
+
+        let mut tx = self.state.database.begin().await?;
+        let previous_order = image_order_value(&images);
+        let images =
+            Image::set_image_order(&mut *tx, pending_approval.submission_id, image_ids).await?;
+        AuditEntry::add_audit_entry(
+            &mut *tx,
+            AddAuditEntry {
+                actor_id: Some(interaction.user.id.get()),
+                action: AuditAction::EditImages,
+                submission_id: Some(pending_approval.submission_id),
+                before_value: Some(previous_order),
+                after_value: Some(image_order_value(&images)),
+            },
+        )
+        .await?;
+        tx.commit().await?;
+        let votes =
+            ReviewerVote::get_reviewer_votes(&self.state.database, submission.submission_id)
+                .await?;
+
+        modal_response
+            .interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().embeds(approval_embeds(
+                        interaction,
+                        &submission,
+                        &images,
+                        &votes,
+                    )),
+                ),
+            )
+            .await?;
+
+        debug!(
+            "handled an image edit with `message_id`: {message_id}",
+            message_id = interaction.message.id
+        );
+        Ok(())
+    }
+
+    /// Makes sure every pending approval has a message reviewers can act on,
+    /// since approval messages can be deleted while the bot is offline.
+    pub async fn reconcile_pending_approvals(&self, context: &Context) -> anyhow::Result<()> {
+        debug!("reconciling pending approvals");
+
+        let decided_approvals =
+            PendingApproval::remove_decided_approvals(&self.state.database).await?;
+        if !decided_approvals.is_empty() {
+            info!(
+                "removed {count} pending approvals of submissions that were already decided on",
+                count = decided_approvals.len()
+            );
+        }
+
+        let approve_channel = ChannelId::new(self.state.config.channels.approve_id);
+        for pending_approval in
+            PendingApproval::get_all_pending_approvals(&self.state.database).await?
+        {
+            let message_id = MessageId::new(u64::try_from(pending_approval.message_id)?);
+            match approve_channel.message(context, message_id).await {
+                Ok(_) => continue,
+                Err(serenity::Error::Http(error))
+                    if error
+                        .status_code()
+                        .is_some_and(|status_code| status_code.as_u16() == 404) => {}
+                // Anything else doesn't mean the message is gone, so it's
+                // left alone rather than posted twice.
+                Err(error) => {
+                    error!(
+                        "an error occurred while checking the approval message of a submission with `submission_id`: {submission_id}: {error:#?}",
+                        submission_id = pending_approval.submission_id
+                    );
+                    continue;
+                }
+            }
+
+            if let Err(error) = self
+                .repost_approval_message(context, approve_channel, &pending_approval)
+                .await
+            {
+                error!(
+                    "an error occurred while reposting the approval message of a submission with `submission_id`: {submission_id}: {error:#?}",
+                    submission_id = pending_approval.submission_id
+                );
+            }
+        }
+
+        debug!("reconciled pending approvals");
+        Ok(())
+    }
+
+    async fn repost_approval_message(
+        &self,
+        context: &Context,
+        approve_channel: ChannelId,
+        pending_approval: &PendingApproval,
+    ) -> anyhow::Result<()> {
+        let submission = Submission::get_submission(
+            &self.state.database,
+            SubmissionIds::SubmissionId(pending_approval.submission_id),
+        )
+        .await?;
+        let images =
+            Image::get_submission_images(&self.state.database, submission.submission_id).await?;
+        let votes =
+            ReviewerVote::get_reviewer_votes(&self.state.database, submission.submission_id)
+                .await?;
+
+        // The user may have left every server the bot can see them in.
+        let author = match UserId::new(u64::try_from(submission.user_id)?)
+            .to_user(context)
+            .await
+        {
+            Ok(user) => user.tag(),
+            Err(_) => submission.user_id.to_string(),
+        };
+
+        let reposted_message = approve_channel
+            .send_message(
+                context,
+                approval_message(
+                    &submission,
+                    &images,
+                    &votes,
+                    format!("Submitted by {author}"),
+                    Timestamp::from_unix_timestamp(
+                        submission.submission_date.and_utc().timestamp(),
+                    )
+                    .unwrap_or_else(|_| Timestamp::now()),
+                ),
+            )
+            .await?;
+        PendingApproval::update_approval_message(
+            &self.state.database,
+            submission.submission_id,
+            reposted_message.id.get(),
+        )
+        .await?;
+
+        info!(
+            "reposted the deleted approval message of a submission with: `submission_id`: {submission_id}",
+            submission_id = submission.submission_id
+        );
+        Ok(())
+    }
+
+    /// Finds the pending approval a reviewer wants to edit, letting them know
+    /// if they can't.
+    async fn get_editable_approval(
+        &self,
+        interaction: &ComponentInteraction,
+        context: &Context,
+    ) -> anyhow::Result<Option<PendingApproval>> {
+        if !self.state.config.team.contains(&interaction.user.id) {
+            interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("You don't have enough permissions to do that.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+
+            return Ok(None);
+        }
+
+        // The approval isn't kept locked while waiting on a modal, since that
+        // can take however long the reviewer needs to fill it in.
+        let pending_approval = self
+            .state
+            .data
+            .lock_pending_approval(i64::try_from(interaction.message.id.get())?)
+            .await
+            .as_deref()
+            .cloned();
+
+        if pending_approval.is_none() {
+            interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("This approval does not exist.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+        }
+
+        Ok(pending_approval)
+    }
+
+    /// Locks the approval again once the reviewer is done with the modal,
+    /// since it may have been decided on while it was open. Gives the
+    /// submission as it is now, letting them know if it can't be edited
+    /// anymore.
+    async fn relock_editable_approval(
+        &self,
+        modal_interaction: &ModalInteraction,
+        context: &Context,
+        pending_approval: &PendingApproval,
+    ) -> anyhow::Result<Option<(OwnedMutexGuard<PendingApproval>, Submission)>> {
+        let locked_approval = self
+            .state
+            .data
+            .lock_pending_approval(pending_approval.message_id)
+            .await;
+        let submission = Submission::get_submission(
+            &self.state.database,
+            SubmissionIds::SubmissionId(pending_approval.submission_id),
+        )
+        .await?;
+
+        let reason = match locked_approval {
+            Some(locked_approval) if submission.decision.is_none() => {
+                return Ok(Some((locked_approval, submission)));
+            }
+            _ if submission.decision.is_some() => {
+                already_decided_reason(&self.state.database, submission.submission_id).await?
+            }
+            _ => String::from("This approval does not exist."),
+        };
+
+        modal_interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(reason)
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+
+        Ok(None)
+    }
+}
+
+/// Replies so only the reviewer can see it, as a follow-up if the response
+/// was deferred.
+async fn respond_ephemeral(
+    interaction: &ComponentInteraction,
+    context: &Context,
+    deferred: bool,
+    content: String,
+) -> anyhow::Result<()> {
+    if deferred {
+        interaction
+            .create_followup(
+                &context.http,
+                CreateInteractionResponseFollowup::new()
+                    .content(content)
+                    .ephemeral(true),
+            )
+            .await?;
+    } else {
+        interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(content)
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Rebuilds the embeds of an approval message, keeping who submitted it and
+/// when from the original message.
+fn approval_embeds(
+    interaction: &ComponentInteraction,
+    submission: &Submission,
+    images: &[Image],
+    votes: &[ReviewerVote],
+) -> Vec<CreateEmbed<'static>> {
+    let original_embed = interaction.message.embeds.first();
+
+    submission_embeds(
+        submission,
+        images,
+        votes,
+        original_embed
+            .and_then(|embed| embed.author.as_ref())
+            .map(|author| author.name.to_string())
+            .unwrap_or_default(),
+        original_embed
+            .and_then(|embed| embed.timestamp)
+            .unwrap_or_else(Timestamp::now),
+    )
+}
+
+/// Images of a submission in their order, as IDs since their links can be
+/// long.
+fn image_order_value(images: &[Image]) -> String {
+    images
+        .iter()
+        .map(|image| image.image_id.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses a comma separated list of 1-based image numbers into image IDs.
+/// At least one image has to be kept, a submission without any has nothing
+/// to post.
+fn parse_image_order(order: &str, images: &[Image]) -> Option<Vec<i32>> {
+    let mut image_ids = vec![];
+    for number in order
+        .split(|character: char| character == ',' || character.is_whitespace())
+        .filter(|number| !number.is_empty())
+    {
+        let image_id = images
+            .get(number.parse::<usize>().ok()?.checked_sub(1)?)?
+            .image_id;
+        if image_ids.contains(&image_id) {
+            return None;
+        }
+
+        image_ids.push(image_id);
+    }
+
+    Some(image_ids).filter(|image_ids| !image_ids.is_empty())
 }
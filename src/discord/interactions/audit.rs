@@ -0,0 +1,171 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue},
+    builder::{
+        CreateCommand, CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    client::Context,
+};
+
+use crate::{
+    discord::YuriState,
+    models::audit_log::{AuditAction, AuditEntry, AuditEntryHelpers, AuditFilter},
+};
+
+use super::YuriInteraction;
+
+/// Keeps the listing within the length Discord allows for embed descriptions.
+const MAX_AUDIT_ENTRIES: i64 = 15;
+const MAX_VALUE_LENGTH: usize = 80;
+
+const AUDIT_ACTIONS: &[(&str, AuditAction)] = &[
+    ("approve", AuditAction::Approve),
+    ("reject", AuditAction::Reject),
+    ("edit_alt_text", AuditAction::EditAltText),
+    ("edit_images", AuditAction::EditImages),
+    ("pause_posting", AuditAction::PausePosting),
+    ("resume_posting", AuditAction::ResumePosting),
+    ("expire", AuditAction::Expire),
+    ("import_backlog", AuditAction::ImportBacklog),
+];
+
+pub struct Interaction;
+impl YuriInteraction for Interaction {
+    fn register() -> CreateCommand<'static> {
+        let mut action_option = CreateCommandOption::new(
+            CommandOptionType::String,
+            "action",
+            "Only show this kind of action.",
+        );
+        for (name, _) in AUDIT_ACTIONS {
+            action_option = action_option.add_string_choice(*name, *name);
+        }
+
+        CreateCommand::new("audit")
+            .description("Search the log of moderation actions.")
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Integer,
+                "submission",
+                "Only show actions on this submission.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::User,
+                "user",
+                "Only show actions taken by this user.",
+            ))
+            .add_option(action_option)
+    }
+
+    async fn run(
+        context: &Context,
+        interaction: &CommandInteraction,
+        state: Arc<YuriState>,
+        options: &[ResolvedOption<'_>],
+    ) -> anyhow::Result<()> {
+        if !state.config.team.contains(&interaction.user.id) {
+            interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("You don't have enough permissions to do that.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let mut audit_filter = AuditFilter::default();
+        for option in options {
+            match (option.name, &option.value) {
+                ("submission", ResolvedValue::Integer(submission_id)) => {
+                    audit_filter.submission_id = Some(i32::try_from(*submission_id)?);
+                }
+                ("user", ResolvedValue::User(user, _)) => {
+                    audit_filter.actor_id = Some(user.id.get());
+                }
+                ("action", ResolvedValue::String(action)) => {
+                    audit_filter.action = AUDIT_ACTIONS
+                        .iter()
+                        .find(|(name, _)| name == action)
+                        .map(|(_, action)| *action);
+                }
+                _ => {}
+            }
+        }
+
+        let audit_entries =
+            AuditEntry::search_audit_log(&state.database, audit_filter, MAX_AUDIT_ENTRIES).await?;
+
+        let description = if audit_entries.is_empty() {
+            String::from("Nothing was found.")
+        } else {
+            audit_entries
+                .iter()
+                .map(audit_line)
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .embed(
+                            CreateEmbed::new()
+                                .title("Audit Log")
+                                .description(description),
+                        )
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
+
+fn audit_line(audit_entry: &AuditEntry) -> String {
+    format!(
+        "<t:{timestamp}:f> {actor} **{action}**{submission}: {before} → {after}",
+        timestamp = audit_entry.audit_date.and_utc().timestamp(),
+        actor = match (audit_entry.actor_id, audit_entry.action) {
+            (Some(actor_id), _) => format!("<@{actor_id}>"),
+            // Approvals expire on their own.
+            (None, AuditAction::Expire) => String::from("Nobody"),
+            (None, _) => String::from("Command line"),
+        },
+        action = AUDIT_ACTIONS
+            .iter()
+            .find(|(_, action)| *action == audit_entry.action)
+            .map_or("unknown", |(name, _)| name),
+        submission = audit_entry
+            .submission_id
+            .map(|submission_id| format!(" on submission {submission_id}"))
+            .unwrap_or_default(),
+        before = audit_value(audit_entry.before_value.as_deref()),
+        after = audit_value(audit_entry.after_value.as_deref()),
+    )
+}
+
+fn audit_value(value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return String::from("*nothing*");
+    };
+
+    // Backticks in the value would end the code span early.
+    let value = value.replace('`', "'");
+    if value.chars().count() > MAX_VALUE_LENGTH {
+        format!(
+            "`{value}…`",
+            value = value.chars().take(MAX_VALUE_LENGTH).collect::<String>()
+        )
+    } else {
+        format!("`{value}`")
+    }
+}
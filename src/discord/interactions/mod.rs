@@ -8,7 +8,10 @@ use serenity::{
 
 use super::YuriState;
 
+mod audit;
 mod ping;
+mod post;
+mod queue;
 pub mod yuri;
 
 pub trait YuriInteraction {
@@ -25,7 +28,13 @@ pub trait YuriInteraction {
 pub async fn register_interactions(guild_id: GuildId, context: &Context) {
     debug!("registering guild interactions");
 
-    let interactions = &[ping::Interaction::register(), yuri::Interaction::register()];
+    let interactions = &[
+        audit::Interaction::register(),
+        ping::Interaction::register(),
+        post::Interaction::register(),
+        queue::Interaction::register(),
+        yuri::Interaction::register(),
+    ];
 
     match guild_id.set_commands(&context.http, interactions).await {
         Ok(commands) => info!(
@@ -48,7 +57,10 @@ pub async fn run_interactions(
     options: &[ResolvedOption<'_>],
 ) -> anyhow::Result<()> {
     match command_name {
+        "audit" => audit::Interaction::run(context, interaction, state, options).await,
         "ping" => ping::Interaction::run(context, interaction, state, options).await,
+        "post" => post::Interaction::run(context, interaction, state, options).await,
+        "queue" => queue::Interaction::run(context, interaction, state, options).await,
         "yuri" => yuri::Interaction::run(context, interaction, state, options).await,
         _ => Ok(()),
     }
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{CommandInteraction, CommandOptionType, ResolvedOption, ResolvedValue},
+    builder::{
+        CreateCommand, CreateCommandOption, CreateInteractionResponse,
+        CreateInteractionResponseMessage,
+    },
+    client::Context,
+};
+
+use crate::{
+    discord::YuriState,
+    models::scheduler_state::{
+        SchedulerState, SchedulerStateHelpers, SchedulerStatus, UpdateSchedulerState,
+    },
+    scheduler,
+};
+
+use super::YuriInteraction;
+
+pub struct Interaction;
+impl YuriInteraction for Interaction {
+    fn register() -> CreateCommand<'static> {
+        CreateCommand::new("post")
+            .description("Control the posting scheduler.")
+            .add_option(
+                CreateCommandOption::new(
+                    CommandOptionType::SubCommand,
+                    "pause",
+                    "Pause posting, submissions keep being queued.",
+                )
+                .add_sub_option(CreateCommandOption::new(
+                    CommandOptionType::String,
+                    "reason",
+                    "Why posting is paused.",
+                )),
+            )
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "resume",
+                "Resume posting.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::SubCommand,
+                "status",
+                "Show whether posting is running or paused.",
+            ))
+    }
+
+    async fn run(
+        context: &Context,
+        interaction: &CommandInteraction,
+        state: Arc<YuriState>,
+        options: &[ResolvedOption<'_>],
+    ) -> anyhow::Result<()> {
+        if !state.config.team.contains(&interaction.user.id) {
+            interaction
+                .create_response(
+                    &context.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("You don't have enough permissions to do that.")
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+
+            return Ok(());
+        }
+
+        let Some(subcommand) = options.first() else {
+            return Ok(());
+        };
+
+        let scheduler_state = match (subcommand.name, &subcommand.value) {
+            ("pause", ResolvedValue::SubCommand(options)) => {
+                let reason = options.iter().find_map(|option| match option.value {
+                    ResolvedValue::String(reason) if option.name == "reason" => {
+                        Some(reason.to_string())
+                    }
+                    _ => None,
+                });
+
+                scheduler::change_scheduler_state(
+                    &state.database,
+                    UpdateSchedulerState {
+                        status: SchedulerStatus::Paused,
+                        reason,
+                        changed_by: Some(interaction.user.id.get()),
+                    },
+                )
+                .await?
+            }
+            ("resume", _) => {
+                scheduler::change_scheduler_state(
+                    &state.database,
+                    UpdateSchedulerState {
+                        status: SchedulerStatus::Running,
+                        reason: None,
+                        changed_by: Some(interaction.user.id.get()),
+                    },
+                )
+                .await?
+            }
+            _ => SchedulerState::get_scheduler_state(&state.database).await?,
+        };
+
+        let content = match scheduler_state.status {
+            SchedulerStatus::Running => String::from("Posting is running."),
+            SchedulerStatus::Paused => format!(
+                "Posting is paused{changed_by}: {reason}\nSubmissions will keep being queued until it's resumed.",
+                changed_by = scheduler_state
+                    .changed_by
+                    .map(|user_id| format!(" by <@{user_id}>"))
+                    .unwrap_or_default(),
+                reason = scheduler_state
+                    .reason
+                    .as_deref()
+                    .unwrap_or("*No reason provided*")
+            ),
+        };
+
+        interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(content)
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
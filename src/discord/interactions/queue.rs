@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{CommandInteraction, ResolvedOption},
+    builder::{CreateCommand, CreateInteractionResponse, CreateInteractionResponseMessage},
+    client::Context,
+};
+
+use crate::{
+    discord::YuriState,
+    models::{
+        scheduler_state::{SchedulerState, SchedulerStateHelpers, SchedulerStatus},
+        submissions::{Submission, SubmissionHelpers},
+    },
+};
+
+use super::YuriInteraction;
+
+pub struct Interaction;
+impl YuriInteraction for Interaction {
+    fn register() -> CreateCommand<'static> {
+        CreateCommand::new("queue")
+            .description("Show how many posts are queued and when the next one is.")
+    }
+
+    async fn run(
+        context: &Context,
+        interaction: &CommandInteraction,
+        state: Arc<YuriState>,
+        _options: &[ResolvedOption<'_>],
+    ) -> anyhow::Result<()> {
+        let queued_submissions = Submission::count_queued_submissions(
+            &state.database,
+            state.config.submissions.require_alt_text,
+            &state.config.targets,
+        )
+        .await?;
+        let scheduler_state = SchedulerState::get_scheduler_state(&state.database).await?;
+        let next_post = *state.scheduler_clock.next_post.read().await;
+
+        let next_post = match (scheduler_state.status, next_post) {
+            (SchedulerStatus::Paused, _) => String::from("Posting is paused."),
+            (SchedulerStatus::Running, Some(next_post)) => format!(
+                "Next post is <t:{timestamp}:R>.",
+                timestamp = next_post.timestamp()
+            ),
+            (SchedulerStatus::Running, None) => {
+                String::from("There are no upcoming posts scheduled.")
+            }
+        };
+
+        interaction
+            .create_response(
+                &context.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content(format!(
+                            "There are {queued_submissions} submissions queued for posting. {next_post}"
+                        ))
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -2,13 +2,13 @@ use std::sync::Arc;
 
 use serenity::{
     all::{
-        ButtonStyle, ChannelId, CommandInteraction, CommandOptionType, CreateEmbedFooter, Mention,
-        ResolvedOption, ResolvedValue,
+        ButtonStyle, ChannelId, CommandInteraction, CommandOptionType, CreateEmbedFooter,
+        InputTextStyle, Mention, ResolvedOption, ResolvedValue, UserId,
     },
     builder::{
         CreateActionRow, CreateButton, CreateCommand, CreateCommandOption, CreateEmbed,
-        CreateEmbedAuthor, CreateInteractionResponse, CreateInteractionResponseMessage,
-        CreateMessage,
+        CreateEmbedAuthor, CreateInputText, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage,
     },
     client::Context,
     model::Timestamp,
@@ -18,13 +18,171 @@ use serenity::{
 use crate::{
     discord::{data::PendingApprovalsHelpers, YuriState},
     models::{
+        images::{AddImages, Image, ImageHelpers, MAX_SUBMISSION_IMAGES},
         pending_approvals::AddPendingApproval,
+        reviewer_votes::ReviewerVote,
         submissions::{AddSubmission, Submission, SubmissionHelpers},
     },
 };
 
 use super::YuriInteraction;
 
+/// Embeds describing a submission on its approval message. Discord shows
+/// embeds sharing the same URL as a single gallery, so every image after the
+/// first one gets an embed of its own.
+pub fn submission_embeds(
+    submission: &Submission,
+    images: &[Image],
+    votes: &[ReviewerVote],
+    author: String,
+    timestamp: Timestamp,
+) -> Vec<CreateEmbed<'static>> {
+    let gallery_url = images.first().map(|image| image.image_path.clone());
+
+    let mut fields = submission_fields(submission, images);
+    if !votes.is_empty() {
+        fields.push((String::from("Votes"), votes_value(votes), false));
+    }
+
+    let mut embed = CreateEmbed::new()
+        .author(CreateEmbedAuthor::new(author))
+        .fields(fields)
+        .timestamp(timestamp)
+        .footer(CreateEmbedFooter::new(format!(
+            "Submission Id: {}",
+            submission.submission_id
+        )));
+    if let Some(gallery_url) = &gallery_url {
+        embed = embed.url(gallery_url.clone());
+    }
+
+    let mut images = images.iter();
+    if let Some(image) = images.next() {
+        embed = embed.image(image.image_path.clone());
+    }
+
+    std::iter::once(embed)
+        .chain(images.map(|image| {
+            CreateEmbed::new()
+                .url(gallery_url.clone().unwrap_or_default())
+                .image(image.image_path.clone())
+        }))
+        .collect()
+}
+
+/// The message reviewers decide on a submission with.
+pub fn approval_message(
+    submission: &Submission,
+    images: &[Image],
+    votes: &[ReviewerVote],
+    author: String,
+    timestamp: Timestamp,
+) -> CreateMessage<'static> {
+    let mut buttons = vec![
+        CreateButton::new("approve")
+            .label("Approve")
+            .style(ButtonStyle::Success),
+        CreateButton::new("reject")
+            .label("Reject")
+            .style(ButtonStyle::Danger),
+        CreateButton::new("edit_alt_text")
+            .label("Edit Alt Text")
+            .style(ButtonStyle::Secondary),
+    ];
+    if !images.is_empty() {
+        buttons.push(
+            CreateButton::new("edit_images")
+                .label("Reorder or Drop Images")
+                .style(ButtonStyle::Secondary),
+        );
+    }
+
+    CreateMessage::new()
+        .content("New Yuri Submission!")
+        .embeds(submission_embeds(
+            submission, images, votes, author, timestamp,
+        ))
+        .components(vec![CreateActionRow::Buttons(buttons)])
+}
+
+fn submission_fields(submission: &Submission, images: &[Image]) -> Vec<(String, String, bool)> {
+    let mut fields = vec![
+        (String::from("Artist"), submission.artist.clone(), true),
+        (String::from("Art Link"), submission.art_link.clone(), true),
+        (
+            String::from("Additional Information"),
+            submission
+                .additional_information
+                .clone()
+                .unwrap_or_else(|| String::from("*Not provided*")),
+            false,
+        ),
+    ];
+    // A field each, since every description can be as long as a field.
+    for (index, image) in images.iter().enumerate() {
+        fields.push((
+            alt_text_label(index, images.len()),
+            image
+                .alt_text
+                .clone()
+                .unwrap_or_else(|| String::from("*Not provided*")),
+            false,
+        ));
+    }
+    if let Some(content_warning) = &submission.content_warning {
+        fields.push((
+            String::from("Content Warning"),
+            content_warning.clone(),
+            false,
+        ));
+    }
+
+    fields
+}
+
+/// Names the description of the image at `index`, images are only numbered
+/// when there are several.
+pub fn alt_text_label(index: usize, image_count: usize) -> String {
+    if image_count == 1 {
+        String::from("Image Description (Alt Text)")
+    } else {
+        format!(
+            "Image {position} Description (Alt Text)",
+            position = index + 1
+        )
+    }
+}
+
+/// Who voted which way so far, mentions in embeds don't notify anyone.
+fn votes_value(votes: &[ReviewerVote]) -> String {
+    let reviewers = |approve: bool| {
+        votes
+            .iter()
+            .filter(|vote| vote.approve == approve)
+            .filter_map(|vote| u64::try_from(vote.reviewer_id).ok())
+            .map(|reviewer_id| Mention::from(UserId::new(reviewer_id)).to_string())
+            .collect::<Vec<_>>()
+    };
+
+    [
+        ("Approved by", reviewers(true)),
+        ("Rejected by", reviewers(false)),
+    ]
+    .into_iter()
+    .filter(|(_, reviewers)| !reviewers.is_empty())
+    .map(|(label, reviewers)| format!("{label} {reviewers}", reviewers = reviewers.join(", ")))
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Input for an image description, embed field values can't be longer than
+/// 1024 characters.
+pub fn alt_text_input(label: String) -> CreateInputText<'static> {
+    CreateInputText::new(InputTextStyle::Paragraph, label, "")
+        .required(false)
+        .max_length(1000)
+}
+
 pub struct Interaction;
 impl YuriInteraction for Interaction {
     fn register() -> CreateCommand<'static> {
@@ -35,6 +193,21 @@ impl YuriInteraction for Interaction {
                 "sample",
                 "Sample of the content to be submitted to help us decide quicker!",
             ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                "sample-2",
+                "Second image, for sets or comics with several pages.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                "sample-3",
+                "Third image, for sets or comics with several pages.",
+            ))
+            .add_option(CreateCommandOption::new(
+                CommandOptionType::Attachment,
+                "sample-4",
+                "Fourth image, for sets or comics with several pages.",
+            ))
     }
 
     async fn run(
@@ -43,91 +216,97 @@ impl YuriInteraction for Interaction {
         state: Arc<YuriState>,
         options: &[ResolvedOption<'_>],
     ) -> anyhow::Result<()> {
-        if let Some(modal_response) = interaction
-            .quick_modal(
-                context,
-                CreateQuickModal::new("Submit Yuri")
-                    .short_field("Artist's Name or Link")
-                    .short_field("Art's Link")
-                    .paragraph_field("Additional Information"),
-            )
-            .await?
-        {
-            let (artist, art_link, additional_information, sample_image_url) = {
+        // Options aren't guaranteed to arrive in the order they were
+        // registered in.
+        let image_paths = {
+            let mut attachments = options
+                .iter()
+                .filter_map(|option| {
+                    if let ResolvedValue::Attachment(attachment) = option.value {
+                        Some((option.name, attachment.url.to_string()))
+                    } else {
+                        None
+                    }
+                })
+                .collect::<Vec<_>>();
+            attachments.sort_by_key(|(name, _)| *name);
+
+            attachments
+                .into_iter()
+                .map(|(_, url)| url)
+                .take(MAX_SUBMISSION_IMAGES)
+                .collect::<Vec<_>>()
+        };
+
+        // The form only has room for one description, several images are
+        // described one by one with **Edit Alt Text** instead.
+        let single_image = image_paths.len() == 1;
+        let mut submission_modal = CreateQuickModal::new("Submit Yuri")
+            .short_field("Artist's Name or Link")
+            .short_field("Art's Link")
+            .paragraph_field("Additional Information");
+        if single_image {
+            submission_modal = submission_modal.field(alt_text_input(alt_text_label(0, 1)));
+        }
+        submission_modal = submission_modal.field(
+            CreateInputText::new(InputTextStyle::Short, "Content Warning", "")
+                .placeholder("Leave empty if the art is safe to show as is")
+                .required(false)
+                .max_length(200),
+        );
+
+        if let Some(modal_response) = interaction.quick_modal(context, submission_modal).await? {
+            let (artist, art_link, additional_information, alt_text, content_warning) = {
                 let inputs = modal_response.inputs;
 
                 (
                     inputs[0].to_string(),
                     inputs[1].to_string(),
                     inputs[2].to_string(),
-                    options.first().and_then(|option| {
-                        if let ResolvedValue::Attachment(attachment) = option.value {
-                            Some(attachment.url.to_string())
-                        } else {
-                            None
-                        }
-                    }),
+                    single_image
+                        .then(|| inputs[3].trim().to_string())
+                        .filter(|alt_text| !alt_text.is_empty()),
+                    Some(inputs[inputs.len() - 1].trim().to_string())
+                        .filter(|content_warning| !content_warning.is_empty()),
                 )
             };
 
-            match Submission::add_submission(
-                &state.database,
+            match add_submission(
+                &state,
                 AddSubmission {
                     user_id: interaction.user.id.get(),
                     artist,
                     art_link,
                     additional_information: Some(additional_information),
-                    sample_image_url,
+                    content_warning,
                 },
+                image_paths,
+                vec![alt_text],
             )
             .await
             {
-                Ok(submission_table) => {
-                    let embed = CreateEmbed::new()
-                        .author(CreateEmbedAuthor::new(format!(
-                            "Submitted by {user_tag}",
-                            user_tag = interaction.user.tag()
-                        )))
-                        .fields(vec![
-                            ("Artist", submission_table.artist, true),
-                            ("Art Link", submission_table.art_link, true),
-                            (
-                                "Additional Information",
-                                submission_table
-                                    .additional_information
-                                    .unwrap_or_else(|| String::from("*Not provided*")),
-                                false,
-                            ),
-                        ])
-                        .timestamp(Timestamp::now())
-                        .footer(CreateEmbedFooter::new(format!(
-                            "Submission Id: {}",
-                            submission_table.submission_id
-                        )))
-                        .image(submission_table.sample_image_url.unwrap_or_default());
+                Ok((submission_table, images)) => {
+                    metrics::counter!("yuri_submissions_total").increment(1);
 
                     let submission_approval_message =
                         ChannelId::new(state.config.channels.approve_id)
                             .send_message(
                                 context,
-                                CreateMessage::new()
-                                    .content("New Yuri Submission!")
-                                    .embed(embed.clone())
-                                    .components(vec![CreateActionRow::Buttons(vec![
-                                        CreateButton::new("approve")
-                                            .label("Approve")
-                                            .style(ButtonStyle::Success),
-                                        CreateButton::new("reject")
-                                            .label("Reject")
-                                            .style(ButtonStyle::Danger),
-                                    ])]),
+                                approval_message(
+                                    &submission_table,
+                                    &images,
+                                    &[],
+                                    format!(
+                                        "Submitted by {user_tag}",
+                                        user_tag = interaction.user.tag()
+                                    ),
+                                    Timestamp::now(),
+                                ),
                             )
                             .await?;
 
                     state
                         .data
-                        .lock()
-                        .await
                         .add_pending_approval(
                             &state.database,
                             AddPendingApproval {
@@ -148,6 +327,19 @@ impl YuriInteraction for Interaction {
                                 ),
                             )
                             .await?;
+
+                    // The attachment links expire, so the originals are kept
+                    // before they do. Posting downloads them otherwise.
+                    if let Err(error) = state
+                        .media
+                        .store_originals(&state.database, submission_table.submission_id)
+                        .await
+                    {
+                        error!(
+                            "an error occurred while storing the originals of a submission with `submission_id`: {submission_id}: {error:#?}",
+                            submission_id = submission_table.submission_id
+                        );
+                    }
                 }
                 Err(error) => {
                     error!("error submitting yuri addition: {:#?}", error);
@@ -167,3 +359,25 @@ impl YuriInteraction for Interaction {
         Ok(())
     }
 }
+
+async fn add_submission(
+    state: &YuriState,
+    submission: AddSubmission,
+    image_paths: Vec<String>,
+    alt_texts: Vec<Option<String>>,
+) -> anyhow::Result<(Submission, Vec<Image>)> {
+    let mut tx = state.database.begin().await?;
+    let submission = Submission::add_submission(&mut *tx, submission).await?;
+    let images = Image::add_images(
+        &mut *tx,
+        AddImages {
+            submission_id: submission.submission_id,
+            image_paths,
+            alt_texts,
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok((submission, images))
+}
@@ -1,22 +1,29 @@
 // TODO: Silly-ify responses of the bot!
 
-use std::sync::Arc;
+use std::sync::{atomic::AtomicBool, Arc};
 
 use serenity::{
     all::{GatewayIntents, GuildId, UserId},
     Client,
 };
 use sqlx::PgPool;
-use tokio::sync::Mutex;
 
-use crate::config::{DiscordChannelConfig, DiscordConfig};
+use crate::{
+    config::{DiscordChannelConfig, DiscordConfig, SubmissionsConfig},
+    media::YuriMedia,
+    scheduler::SchedulerClock,
+    shutdown::Shutdown,
+};
 
 use self::data::YuriData;
+pub use self::moderation_log::{ModerationEvent, ModerationLog};
 
-mod data;
+pub mod approvals;
+pub mod data;
 mod event_handler;
 mod handlers;
 mod interactions;
+mod moderation_log;
 
 pub struct YuriDiscord {
     token: String,
@@ -25,30 +32,65 @@ pub struct YuriDiscord {
 
 pub struct YuriState {
     pub database: PgPool,
+    pub media: YuriMedia,
     pub config: YuriConfig,
-    pub data: Arc<Mutex<YuriData>>,
+    pub data: YuriData,
+    pub scheduler_clock: Arc<SchedulerClock>,
+    pub gateway_status: Arc<GatewayStatus>,
+    pub moderation_log: ModerationLog,
+    pub shutdown: Shutdown,
+}
+
+#[derive(Default)]
+pub struct GatewayStatus {
+    pub connected: AtomicBool,
 }
 
 pub struct YuriConfig {
     pub channels: DiscordChannelConfig,
+    pub submissions: SubmissionsConfig,
+    /// Targets the scheduler publishes to, see the queue helpers.
+    pub targets: Vec<String>,
     pub team: Vec<UserId>,
+    pub admins: Vec<UserId>,
     pub server_id: GuildId,
 }
 
 impl YuriDiscord {
-    pub fn new(discord_config: DiscordConfig, database: PgPool) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        discord_config: DiscordConfig,
+        submissions_config: SubmissionsConfig,
+        targets: Vec<String>,
+        database: PgPool,
+        media: YuriMedia,
+        scheduler_clock: Arc<SchedulerClock>,
+        gateway_status: Arc<GatewayStatus>,
+        moderation_log: ModerationLog,
+        shutdown: Shutdown,
+    ) -> Self {
         Self {
             token: discord_config.token,
             state: YuriState {
                 database,
+                media,
                 config: YuriConfig {
                     channels: discord_config.channels,
+                    submissions: submissions_config,
+                    targets,
                     team: discord_config.team.iter().map(|id| (*id).into()).collect(),
+                    admins: discord_config
+                        .admins
+                        .iter()
+                        .map(|id| (*id).into())
+                        .collect(),
                     server_id: discord_config.server_id.into(),
                 },
-                data: Arc::new(Mutex::new(YuriData {
-                    pending_approvals: Vec::default(),
-                })),
+                data: YuriData::default(),
+                scheduler_clock,
+                gateway_status,
+                moderation_log,
+                shutdown,
             }
             .into(),
         }
@@ -61,6 +103,18 @@ impl YuriDiscord {
             })
             .await?;
 
+        let shard_manager = client.shard_manager.clone();
+        let shutdown = self.state.shutdown.clone();
+        tokio::spawn(async move {
+            shutdown.triggered().await;
+            // Interactions that already started get to finish before the
+            // shards go away, `client.start` returns once they're shut down.
+            shutdown.finished().await;
+
+            debug!("shutting down Discord shards");
+            shard_manager.shutdown_all().await;
+        });
+
         debug!("spawning Discord bot");
         tokio::spawn(async move { client.start().await }).await??;
 
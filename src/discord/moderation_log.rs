@@ -0,0 +1,212 @@
+use std::sync::Arc;
+
+use serenity::{
+    all::{
+        ChannelId, Colour, CreateEmbed, CreateEmbedFooter, CreateMessage, GuildId, Mention,
+        MessageId, UserId,
+    },
+    http::Http,
+};
+
+/// Errors in the log are cut down to this, a failure's embed is meant to be
+/// a glance and the full error is in the reviewer channel.
+const MAX_ERROR_LENGTH: usize = 200;
+
+/// Mirrors moderation events to the log channel, if there's one.
+#[derive(Clone)]
+pub struct ModerationLog {
+    pub http: Arc<Http>,
+    pub channel_id: Option<ChannelId>,
+    pub server_id: GuildId,
+}
+
+pub enum ModerationEvent {
+    /// A reviewer's vote was counted without deciding, `votes` is how many
+    /// there are for the same decision so far.
+    Voted {
+        submission_id: i32,
+        reviewer_id: UserId,
+        approve: bool,
+        votes: usize,
+        quorum: u32,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    Approved {
+        submission_id: i32,
+        reviewer_id: UserId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    Rejected {
+        submission_id: i32,
+        reviewer_id: UserId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    Expired {
+        submission_id: i32,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    },
+    Published {
+        submission_id: i32,
+        target: String,
+        status_url: Option<String>,
+    },
+    /// `alert` is the message reviewers were warned with, once the post is
+    /// given up on.
+    PostFailed {
+        submission_id: i32,
+        target: String,
+        error: String,
+        alert: Option<(ChannelId, MessageId)>,
+    },
+}
+
+impl ModerationLog {
+    /// Failing to log an event is only reported, since whatever was logged
+    /// already happened.
+    pub async fn log(&self, moderation_event: ModerationEvent) {
+        let Some(channel_id) = self.channel_id else {
+            return;
+        };
+
+        if let Err(error) = channel_id
+            .send_message(
+                &self.http,
+                CreateMessage::new().embed(self.event_embed(moderation_event)),
+            )
+            .await
+        {
+            error!("an error occurred while logging a moderation event: {error:#?}");
+        }
+    }
+
+    fn event_embed(&self, moderation_event: ModerationEvent) -> CreateEmbed<'static> {
+        let (title, description, colour, submission_id) = match moderation_event {
+            ModerationEvent::Voted {
+                submission_id,
+                reviewer_id,
+                approve,
+                votes,
+                quorum,
+                channel_id,
+                message_id,
+            } => (
+                format!(
+                    "Voted to {decision} ({votes}/{quorum})",
+                    decision = if approve { "approve" } else { "reject" }
+                ),
+                format!(
+                    "By {reviewer} · [Jump to approval]({link})",
+                    reviewer = Mention::from(reviewer_id),
+                    link = message_id.link(channel_id, Some(self.server_id))
+                ),
+                Colour::LIGHT_GREY,
+                submission_id,
+            ),
+            ModerationEvent::Approved {
+                submission_id,
+                reviewer_id,
+                channel_id,
+                message_id,
+            } => (
+                String::from("Approved"),
+                format!(
+                    "By {reviewer} · [Jump to approval]({link})",
+                    reviewer = Mention::from(reviewer_id),
+                    link = message_id.link(channel_id, Some(self.server_id))
+                ),
+                Colour::DARK_GREEN,
+                submission_id,
+            ),
+            ModerationEvent::Rejected {
+                submission_id,
+                reviewer_id,
+                channel_id,
+                message_id,
+            } => (
+                String::from("Rejected"),
+                format!(
+                    "By {reviewer} · [Jump to approval]({link})",
+                    reviewer = Mention::from(reviewer_id),
+                    link = message_id.link(channel_id, Some(self.server_id))
+                ),
+                Colour::RED,
+                submission_id,
+            ),
+            ModerationEvent::Expired {
+                submission_id,
+                channel_id,
+                message_id,
+            } => (
+                String::from("Expired"),
+                format!(
+                    "Nobody reviewed it in time · [Jump to approval]({link})",
+                    link = message_id.link(channel_id, Some(self.server_id))
+                ),
+                Colour::DARK_GREY,
+                submission_id,
+            ),
+            ModerationEvent::Published {
+                submission_id,
+                target,
+                status_url,
+            } => (
+                format!("Posted to {target}"),
+                status_url
+                    .map(|status_url| format!("[Jump to post]({status_url})"))
+                    .unwrap_or_default(),
+                Colour::BLUE,
+                submission_id,
+            ),
+            ModerationEvent::PostFailed {
+                submission_id,
+                target,
+                error,
+                alert,
+            } => {
+                let error = if error.chars().count() > MAX_ERROR_LENGTH {
+                    format!(
+                        "{error}…",
+                        error = error.chars().take(MAX_ERROR_LENGTH).collect::<String>()
+                    )
+                } else {
+                    error
+                };
+
+                match alert {
+                    Some((channel_id, message_id)) => (
+                        format!("Gave up on posting to {target}"),
+                        format!(
+                            "{error} · [Jump to alert]({link})",
+                            link = message_id.link(channel_id, Some(self.server_id))
+                        ),
+                        Colour::DARK_RED,
+                        submission_id,
+                    ),
+                    None => (
+                        format!("Failed to post to {target}"),
+                        error,
+                        Colour::ORANGE,
+                        submission_id,
+                    ),
+                }
+            }
+        };
+
+        let embed = CreateEmbed::new()
+            .title(title)
+            .colour(colour)
+            .footer(CreateEmbedFooter::new(format!(
+                "Submission Id: {submission_id}"
+            )));
+
+        if description.is_empty() {
+            embed
+        } else {
+            embed.description(description)
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::{fmt::Write, path::Path, sync::Arc};
+
+use sqlx::PgPool;
+
+use crate::{
+    config::FeedConfig,
+    media,
+    models::{
+        images::{Image, ImageHelpers},
+        posts::{Post, PostHelpers},
+        submissions::{Submission, SubmissionHelpers, SubmissionIds},
+    },
+    scheduler::{self, SchedulerClock},
+    shutdown::Shutdown,
+};
+
+pub struct YuriFeed {
+    config: FeedConfig,
+    media_storage_path: std::path::PathBuf,
+    database: PgPool,
+}
+
+impl YuriFeed {
+    pub const fn new(
+        feed_config: FeedConfig,
+        media_storage_path: std::path::PathBuf,
+        database: PgPool,
+    ) -> Self {
+        Self {
+            config: feed_config,
+            media_storage_path,
+            database,
+        }
+    }
+
+    /// Writes the feed on start and again every time a submission finishes
+    /// posting.
+    pub async fn spawn(
+        &self,
+        clock: Arc<SchedulerClock>,
+        shutdown: &Shutdown,
+    ) -> anyhow::Result<()> {
+        debug!("spawning feed writer");
+
+        loop {
+            if let Err(error) = self.write().await {
+                error!("an error occurred while writing the feed: {error:#?}");
+            }
+
+            tokio::select! {
+                () = clock.posted.notified() => {}
+                () = shutdown.triggered() => return Ok(()),
+            }
+        }
+    }
+
+    pub async fn write(&self) -> anyhow::Result<()> {
+        let feed = self.render().await?;
+
+        // Written next to the feed first, so whatever serves it never reads
+        // a partially written file.
+        let temporary_path = self.config.path.with_extension("tmp");
+        if let Some(parent) = self.config.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&temporary_path, feed).await?;
+        tokio::fs::rename(&temporary_path, &self.config.path).await?;
+
+        debug!(
+            "wrote the feed to `{path}`",
+            path = self.config.path.display()
+        );
+        Ok(())
+    }
+
+    async fn render(&self) -> anyhow::Result<String> {
+        let published_posts =
+            Post::get_published_posts(&self.database, self.config.entries).await?;
+
+        // Posts come newest first, grouped into one entry per submission.
+        let mut entries: Vec<Vec<Post>> = vec![];
+        for post in published_posts {
+            match entries
+                .iter_mut()
+                .find(|posts| posts[0].submission_id == post.submission_id)
+            {
+                Some(posts) => posts.push(post),
+                None => entries.push(vec![post]),
+            }
+        }
+
+        let updated = entries
+            .first()
+            .and_then(|posts| posts[0].publish_date)
+            .map_or_else(chrono::Utc::now, |publish_date| publish_date.and_utc());
+
+        let mut feed = String::from(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+        feed.push('\n');
+        feed.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        write!(
+            feed,
+            r#"<id>{url}</id><title>{title}</title><updated>{updated}</updated><link rel="self" href="{url}"/><generator>{generator}</generator>"#,
+            url = escape(&self.config.url),
+            title = escape(&self.config.title),
+            updated = updated.to_rfc3339(),
+            generator = env!("CARGO_PKG_NAME")
+        )?;
+
+        for mut posts in entries {
+            // The first target a submission went out to stands for the entry.
+            posts.reverse();
+
+            let submission = Submission::get_submission(
+                &self.database,
+                SubmissionIds::SubmissionId(posts[0].submission_id),
+            )
+            .await?;
+            let images =
+                Image::get_submission_images(&self.database, submission.submission_id).await?;
+
+            self.render_entry(&mut feed, &submission, &images, &posts)
+                .await?;
+        }
+
+        feed.push_str("</feed>\n");
+        Ok(feed)
+    }
+
+    async fn render_entry(
+        &self,
+        feed: &mut String,
+        submission: &Submission,
+        images: &[Image],
+        posts: &[Post],
+    ) -> anyhow::Result<()> {
+        let first_post = &posts[0];
+        let published = first_post
+            .publish_date
+            .unwrap_or(first_post.attempt_date)
+            .and_utc()
+            .to_rfc3339();
+
+        write!(
+            feed,
+            r#"<entry><id>urn:uuid:{id}</id><title>Art by {artist}</title><published>{published}</published><updated>{published}</updated><author><name>{artist}</name></author>"#,
+            id = first_post.idempotency_key,
+            artist = escape(&submission.artist),
+        )?;
+
+        let mut linked_alternate = false;
+        for post in posts {
+            let Some(status_url) = &post.status_url else {
+                continue;
+            };
+
+            write!(
+                feed,
+                r#"<link rel="{rel}" title="{target}" href="{status_url}"/>"#,
+                rel = if linked_alternate {
+                    "related"
+                } else {
+                    "alternate"
+                },
+                target = escape(&post.target),
+                status_url = escape(status_url)
+            )?;
+            linked_alternate = true;
+        }
+        write!(
+            feed,
+            r#"<link rel="via" href="{art_link}"/>"#,
+            art_link = escape(&submission.art_link)
+        )?;
+
+        for image in images {
+            feed.push_str(&self.enclosure(image).await);
+        }
+
+        for tag in scheduler::POST_TAGS {
+            write!(feed, r#"<category term="{tag}"/>"#, tag = escape(tag))?;
+        }
+
+        if let Some(content_warning) = &submission.content_warning {
+            write!(
+                feed,
+                r#"<summary type="text">CW: {content_warning}</summary>"#,
+                content_warning = escape(content_warning)
+            )?;
+        }
+        write!(
+            feed,
+            r#"<content type="text">{content}</content></entry>"#,
+            content = escape(&scheduler::post_text(submission, false))
+        )?;
+
+        Ok(())
+    }
+
+    /// Links the stored image when the media storage is served, since links
+    /// to the submitted images don't necessarily stay around.
+    async fn enclosure(&self, image: &Image) -> String {
+        let stored_file = self.config.media_url.as_ref().and_then(|media_url| {
+            let processed_path = Path::new(image.processed_path.as_deref()?);
+            let relative_path = processed_path.strip_prefix(&self.media_storage_path).ok()?;
+
+            Some((
+                processed_path,
+                format!(
+                    "{media_url}/{relative_path}",
+                    media_url = media_url.trim_end_matches('/'),
+                    relative_path = relative_path.to_string_lossy()
+                ),
+            ))
+        });
+
+        if let Some((processed_path, href)) = stored_file {
+            if let Ok(metadata) = tokio::fs::metadata(processed_path).await {
+                let extension = processed_path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or_default();
+
+                return format!(
+                    r#"<link rel="enclosure" type="{mime_type}" length="{length}" href="{href}"/>"#,
+                    mime_type = media::mime_type_for(extension),
+                    length = metadata.len(),
+                    href = escape(&href)
+                );
+            }
+        }
+
+        format!(
+            r#"<link rel="enclosure" href="{href}"/>"#,
+            href = escape(&image.image_path)
+        )
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
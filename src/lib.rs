@@ -0,0 +1,22 @@
+// The model helper traits are only implemented and called in here, so there's
+// no one to add `Send` bounds for.
+#![allow(async_fn_in_trait)]
+
+pub mod archive;
+pub mod backlog;
+pub mod bluesky;
+pub mod config;
+pub mod discord;
+pub mod feed;
+pub mod mastodon;
+pub mod media;
+pub mod migrate;
+pub mod misskey;
+pub mod models;
+pub mod publisher;
+pub mod scheduler;
+pub mod server;
+pub mod shutdown;
+
+#[macro_use]
+extern crate tracing;
@@ -1,16 +1,22 @@
+use std::{sync::Arc, time::Duration};
+
 use clap::Parser;
-use figment::{
-    providers::{Env, Format, Yaml},
-    Figment,
+use serenity::{
+    all::{ChannelId, GuildId},
+    http::Http,
 };
-use figment_file_provider_adapter::FileAdapter;
 use sqlx::PgPool;
 use tracing_subscriber::EnvFilter;
 
+use yuri_every_hour_bot::{
+    archive, backlog, bluesky, config, discord, feed, mastodon, media, migrate, misskey,
+    models::scheduler_state::{
+        SchedulerState, SchedulerStateHelpers, SchedulerStatus, UpdateSchedulerState,
+    },
+    publisher, scheduler, server, shutdown,
+};
+
 mod cli;
-mod config;
-mod discord;
-mod models;
 
 #[macro_use]
 extern crate tracing;
@@ -25,18 +31,330 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     match cli::Cli::parse().subcommand {
-        cli::SubCommands::Start { config } => {
-            let config = Figment::new()
-                .merge(FileAdapter::wrap(Yaml::file(config)).with_suffix("-file"))
-                .merge(FileAdapter::wrap(Env::raw().split("__")))
-                .extract::<config::Config>()?;
+        cli::SubCommands::Start {
+            config: cli::ConfigArgs { config },
+            require_migrated,
+        } => {
+            let config = config::Config::load(&config)?;
 
             let postgres_pool = PgPool::connect(&config.database.url).await?;
-            sqlx::migrate!().run(&postgres_pool).await?;
+            if require_migrated {
+                let pending_migrations = migrate::get_migration_status(&postgres_pool)
+                    .await?
+                    .into_iter()
+                    .filter(|migration| migration.state == migrate::MigrationState::Pending)
+                    .count();
+                if pending_migrations > 0 {
+                    anyhow::bail!(
+                        "there are {pending_migrations} pending migrations, apply them with `migrate run` first"
+                    );
+                }
+            } else {
+                migrate::MIGRATOR.run(&postgres_pool).await?;
+            }
+
+            let mut publishers: Vec<Box<dyn publisher::Publisher>> =
+                vec![Box::new(mastodon::YuriMastodon::new(config.mastodon)?)];
+            if let Some(misskey_config) = config.misskey {
+                publishers.push(Box::new(misskey::YuriMisskey::new(misskey_config)?));
+            }
+            if let Some(bluesky_config) = config.bluesky {
+                publishers.push(Box::new(bluesky::YuriBluesky::new(bluesky_config)?));
+            }
+            let targets = publishers
+                .iter()
+                .map(|publisher| publisher.target().to_string())
+                .collect::<Vec<_>>();
+
+            let discord_http = Arc::new(Http::new(&config.discord.token));
+            let moderation_log = discord::ModerationLog {
+                http: discord_http.clone(),
+                channel_id: config.discord.channels.log_id.map(ChannelId::new),
+                server_id: GuildId::new(config.discord.server_id),
+            };
+
+            let shutdown = shutdown::Shutdown::default();
+            let scheduler_clock = Arc::new(scheduler::SchedulerClock::default());
+            let gateway_status = Arc::new(discord::GatewayStatus::default());
+            let server = config
+                .server
+                .map(|server_config| {
+                    server::YuriServer::new(
+                        server_config,
+                        config.submissions.clone(),
+                        targets.clone(),
+                        postgres_pool.clone(),
+                        scheduler_clock.clone(),
+                        gateway_status.clone(),
+                    )
+                })
+                .transpose()?;
+            let feed = config.feed.map(|feed_config| {
+                feed::YuriFeed::new(
+                    feed_config,
+                    config.media.storage_path.clone(),
+                    postgres_pool.clone(),
+                )
+            });
+            let media = media::YuriMedia::new(config.media)?;
+            let scheduler = scheduler::YuriScheduler::new(
+                config.scheduler,
+                config.submissions.clone(),
+                scheduler_clock.clone(),
+                postgres_pool.clone(),
+                publishers,
+                media.clone(),
+                scheduler::ReviewerChannel {
+                    http: discord_http,
+                    channel_id: ChannelId::new(config.discord.channels.approve_id),
+                    moderation_log: moderation_log.clone(),
+                },
+            )?;
+            let discord = discord::YuriDiscord::new(
+                config.discord,
+                config.submissions,
+                targets,
+                postgres_pool.clone(),
+                media,
+                scheduler_clock.clone(),
+                gateway_status,
+                moderation_log,
+                shutdown.clone(),
+            );
+
+            let running = async {
+                tokio::try_join!(
+                    discord.spawn(),
+                    scheduler.spawn(&shutdown),
+                    async {
+                        match &feed {
+                            Some(feed) => feed.spawn(scheduler_clock, &shutdown).await,
+                            None => Ok(()),
+                        }
+                    },
+                    async {
+                        match &server {
+                            Some(server) => server.spawn(&shutdown).await,
+                            None => Ok(()),
+                        }
+                    }
+                )
+            };
+            tokio::pin!(running);
+
+            tokio::select! {
+                result = &mut running => {
+                    result?;
+                }
+                result = shutdown::signal() => {
+                    result?;
+
+                    info!("shutting down, waiting for work in flight to finish");
+                    shutdown.trigger();
+
+                    match tokio::time::timeout(
+                        Duration::from_secs(config.shutdown_timeout_secs),
+                        &mut running,
+                    )
+                    .await
+                    {
+                        Ok(result) => {
+                            result?;
+                        }
+                        Err(_) => warn!("work in flight didn't finish in time, shutting down anyway"),
+                    }
+                }
+            }
+
+            postgres_pool.close().await;
+            info!("shut down");
+        }
+        cli::SubCommands::Post {
+            config: cli::ConfigArgs { config },
+            subcommand,
+        } => {
+            let config = config::Config::load(&config)?;
+
+            let postgres_pool = PgPool::connect(&config.database.url).await?;
+            migrate::MIGRATOR.run(&postgres_pool).await?;
+
+            let scheduler_state = match subcommand {
+                cli::PostSubCommands::Pause { reason } => {
+                    scheduler::change_scheduler_state(
+                        &postgres_pool,
+                        UpdateSchedulerState {
+                            status: SchedulerStatus::Paused,
+                            reason,
+                            changed_by: None,
+                        },
+                    )
+                    .await?
+                }
+                cli::PostSubCommands::Resume => {
+                    scheduler::change_scheduler_state(
+                        &postgres_pool,
+                        UpdateSchedulerState {
+                            status: SchedulerStatus::Running,
+                            reason: None,
+                            changed_by: None,
+                        },
+                    )
+                    .await?
+                }
+                cli::PostSubCommands::Status => {
+                    SchedulerState::get_scheduler_state(&postgres_pool).await?
+                }
+            };
+
+            info!(
+                "posting is {status:?} since {change_date}: {reason}",
+                status = scheduler_state.status,
+                change_date = scheduler_state.change_date,
+                reason = scheduler_state
+                    .reason
+                    .as_deref()
+                    .unwrap_or("no reason provided")
+            );
+        }
+        cli::SubCommands::Migrate {
+            config: cli::ConfigArgs { config },
+            subcommand,
+        } => {
+            let config = config::Config::load(&config)?;
+            let postgres_pool = PgPool::connect(&config.database.url).await?;
+
+            match subcommand {
+                cli::MigrateSubCommands::Run => {
+                    migrate::MIGRATOR.run(&postgres_pool).await?;
+                    info!("applied all pending migrations");
+                }
+                cli::MigrateSubCommands::Status => {
+                    for migration in migrate::get_migration_status(&postgres_pool).await? {
+                        info!(
+                            "{version} {description}: {state:?}",
+                            version = migration.version,
+                            description = migration.description,
+                            state = migration.state
+                        );
+                    }
+                }
+                cli::MigrateSubCommands::Revert { target } => {
+                    let reverted_migrations =
+                        migrate::revert_migrations(&postgres_pool, target).await?;
+                    info!("reverted {reverted_migrations} migrations");
+                }
+            }
+
+            postgres_pool.close().await;
+        }
+        cli::SubCommands::Export {
+            config: cli::ConfigArgs { config },
+            path,
+            media,
+        } => {
+            let config = config::Config::load(&config)?;
+            let postgres_pool = PgPool::connect(&config.database.url).await?;
+            // A backup may be taken before upgrading, so it's taken as the
+            // database is rather than migrating it first.
+            migrate::ensure_migrated(&postgres_pool).await?;
+
+            let manifest = archive::export(&postgres_pool, &path, media).await?;
+            info!(
+                "exported {records} to `{path}`",
+                records = manifest.records,
+                path = path.display()
+            );
+
+            postgres_pool.close().await;
+        }
+        cli::SubCommands::Import {
+            config: cli::ConfigArgs { config },
+            path,
+        } => {
+            let config = config::Config::load(&config)?;
+            let postgres_pool = PgPool::connect(&config.database.url).await?;
+            migrate::MIGRATOR.run(&postgres_pool).await?;
+
+            let import_report =
+                archive::import(&postgres_pool, &config.media.storage_path, &path).await?;
+            info!(
+                "imported {inserted} out of {archived} from `{path}`",
+                inserted = import_report.inserted,
+                archived = import_report.archived,
+                path = path.display()
+            );
+
+            postgres_pool.close().await;
+        }
+        cli::SubCommands::ImportBacklog {
+            config: cli::ConfigArgs { config },
+            path,
+            report,
+        } => {
+            let config = config::Config::load(&config)?;
+            let Some(system_user_id) = config.submissions.system_user_id else {
+                anyhow::bail!("set `submissions.system_user_id` to import a backlog");
+            };
+
+            let postgres_pool = PgPool::connect(&config.database.url).await?;
+            migrate::MIGRATOR.run(&postgres_pool).await?;
+
+            let report_path = report.unwrap_or_else(|| path.with_extension("skipped.csv"));
+            let backlog_report = backlog::import_backlog(
+                &postgres_pool,
+                &media::YuriMedia::new(config.media)?,
+                system_user_id,
+                &path,
+                &report_path,
+            )
+            .await?;
+
+            info!(
+                "imported {imported} submissions from the backlog",
+                imported = backlog_report.imported
+            );
+            if backlog_report.skipped > 0 {
+                warn!(
+                    "skipped {skipped} rows, they are listed in `{report_path}`",
+                    skipped = backlog_report.skipped,
+                    report_path = report_path.display()
+                );
+            }
+
+            postgres_pool.close().await;
+        }
+        cli::SubCommands::Config {
+            config: cli::ConfigArgs { config },
+            subcommand:
+                cli::ConfigSubCommands::Check {
+                    validate_ids,
+                    print,
+                },
+        } => {
+            let config_check = config::check::check(&config, validate_ids);
+
+            if print {
+                match &config_check.config {
+                    Some(config) => println!(
+                        "{effective_config}",
+                        effective_config =
+                            serde_json::to_string_pretty(&config::check::redacted(config)?)?
+                    ),
+                    None => warn!("can't print the configuration since it couldn't be loaded"),
+                }
+            }
+
+            for problem in &config_check.problems {
+                error!("{problem}");
+            }
+            if !config_check.problems.is_empty() {
+                anyhow::bail!(
+                    "found {count} problems in the configuration",
+                    count = config_check.problems.len()
+                );
+            }
 
-            discord::YuriDiscord::new(config.discord, postgres_pool)
-                .spawn()
-                .await?;
+            info!("configuration is valid");
         }
     }
 
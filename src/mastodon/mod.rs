@@ -0,0 +1,262 @@
+use std::time::{Duration, Instant};
+
+use reqwest::{
+    multipart::{Form, Part},
+    Client, RequestBuilder, Response,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::MastodonConfig,
+    media::Media,
+    publisher::{Publication, Published, Publisher, RetryPolicy},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct YuriMastodon {
+    client: Client,
+    retry_policy: RetryPolicy,
+    instance_host: String,
+    access_token: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MediaAttachment {
+    pub id: String,
+    pub url: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Status {
+    pub id: String,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub media_attachments: Vec<MediaAttachment>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Account {
+    pub id: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateStatus {
+    pub status: String,
+    pub media_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spoiler_text: Option<String>,
+    pub sensitive: bool,
+}
+
+impl YuriMastodon {
+    pub fn new(mastodon_config: MastodonConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            retry_policy: RetryPolicy::new(&mastodon_config.retry),
+            instance_host: mastodon_config.instance_host,
+            access_token: mastodon_config.access_token,
+        })
+    }
+
+    pub async fn upload_media(
+        &self,
+        media: &Media,
+        description: Option<&str>,
+    ) -> anyhow::Result<MediaAttachment> {
+        debug!("uploading `{mime_type}` media", mime_type = media.mime_type);
+
+        // Fail early on a bad MIME type instead of on every retry.
+        Part::bytes(vec![]).mime_str(&media.mime_type)?;
+
+        let media_attachment = self
+            .retry_policy
+            .send(|| {
+                let request = self
+                    .client
+                    .post(self.endpoint("v2/media"))
+                    .bearer_auth(&self.access_token)
+                    .multipart({
+                        let form = Form::new().part(
+                            "file",
+                            Part::bytes(media.bytes.clone())
+                                .file_name(media.file_name.clone())
+                                .mime_str(&media.mime_type)
+                                .expect("MIME type was validated before"),
+                        );
+
+                        match description {
+                            Some(description) => form.text("description", description.to_string()),
+                            None => form,
+                        }
+                    });
+
+                send_timed("v2/media", request)
+            })
+            .await?
+            .json::<MediaAttachment>()
+            .await?;
+
+        debug!(
+            "uploaded media with: `media_id`: {media_id}",
+            media_id = media_attachment.id
+        );
+        Ok(media_attachment)
+    }
+
+    pub async fn publish_status(
+        &self,
+        create_status: &CreateStatus,
+        idempotency_key: uuid::Uuid,
+    ) -> anyhow::Result<Status> {
+        debug!("publishing a status with: `idempotency_key`: {idempotency_key}");
+
+        let status = self
+            .retry_policy
+            .send(|| {
+                send_timed(
+                    "v1/statuses",
+                    self.client
+                        .post(self.endpoint("v1/statuses"))
+                        .bearer_auth(&self.access_token)
+                        .header("Idempotency-Key", idempotency_key.to_string())
+                        .json(create_status),
+                )
+            })
+            .await?
+            .json::<Status>()
+            .await?;
+
+        debug!(
+            "published a status with: `status_id`: {status_id}",
+            status_id = status.id
+        );
+        Ok(status)
+    }
+
+    pub async fn get_own_statuses(&self) -> anyhow::Result<Vec<Status>> {
+        debug!("getting own recent statuses");
+
+        let account = self
+            .retry_policy
+            .send(|| {
+                send_timed(
+                    "v1/accounts/verify_credentials",
+                    self.client
+                        .get(self.endpoint("v1/accounts/verify_credentials"))
+                        .bearer_auth(&self.access_token),
+                )
+            })
+            .await?
+            .json::<Account>()
+            .await?;
+
+        let statuses_endpoint = self.endpoint(&format!(
+            "v1/accounts/{account_id}/statuses",
+            account_id = account.id
+        ));
+        let statuses = self
+            .retry_policy
+            .send(|| {
+                send_timed(
+                    "v1/accounts/statuses",
+                    self.client
+                        .get(&statuses_endpoint)
+                        .bearer_auth(&self.access_token)
+                        .query(&[("limit", "40")]),
+                )
+            })
+            .await?
+            .json::<Vec<Status>>()
+            .await?;
+
+        Ok(statuses)
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        if self.instance_host.starts_with("http://") || self.instance_host.starts_with("https://") {
+            format!("{host}/api/{path}", host = self.instance_host)
+        } else {
+            format!("https://{host}/api/{path}", host = self.instance_host)
+        }
+    }
+}
+
+/// Sends a request and records how long Mastodon took to respond, labelled
+/// with `endpoint` so IDs in paths don't end up in the labels.
+async fn send_timed(endpoint: &'static str, request: RequestBuilder) -> reqwest::Result<Response> {
+    let started = Instant::now();
+    let response = request.send().await;
+    metrics::histogram!("yuri_mastodon_request_duration_seconds", "endpoint" => endpoint)
+        .record(started.elapsed());
+
+    response
+}
+
+#[async_trait::async_trait]
+impl Publisher for YuriMastodon {
+    fn target(&self) -> &'static str {
+        "mastodon"
+    }
+
+    async fn upload_media(
+        &self,
+        media: &Media,
+        description: Option<&str>,
+    ) -> anyhow::Result<String> {
+        Ok(self.upload_media(media, description).await?.id)
+    }
+
+    async fn publish(
+        &self,
+        publication: &Publication,
+        idempotency_key: uuid::Uuid,
+    ) -> anyhow::Result<Published> {
+        let status = self
+            .publish_status(
+                &CreateStatus {
+                    status: publication.text.clone(),
+                    media_ids: publication.media_ids.clone(),
+                    spoiler_text: publication.content_warning.clone(),
+                    sensitive: publication.sensitive,
+                },
+                idempotency_key,
+            )
+            .await?;
+
+        Ok(Published {
+            id: status.id,
+            url: status.url,
+        })
+    }
+
+    async fn find_published(&self, media_ids: &[String]) -> anyhow::Result<Option<Published>> {
+        // Statuses without media can't be told apart, but resending them with
+        // the same idempotency key returns the existing status.
+        if media_ids.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .get_own_statuses()
+            .await?
+            .into_iter()
+            .find(|status| {
+                status
+                    .media_attachments
+                    .iter()
+                    .any(|media_attachment| media_ids.contains(&media_attachment.id))
+            })
+            .map(|status| Published {
+                id: status.id,
+                url: status.url,
+            }))
+    }
+}
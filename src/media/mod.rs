@@ -0,0 +1,289 @@
+use std::{path::Path, sync::Arc, time::Duration};
+
+use sqlx::PgPool;
+
+use crate::{
+    config::MediaConfig,
+    models::images::{Image, ImageHelpers},
+};
+
+mod normalize;
+
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Extensions files are stored with, and how their MIME type is found again
+// when they're read back.
+const MEDIA_TYPES: &[(&str, &str)] = &[
+    ("image/jpeg", "jpg"),
+    ("image/jpeg", "jpeg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+    ("image/avif", "avif"),
+    ("image/heic", "heic"),
+    ("video/mp4", "mp4"),
+    ("video/webm", "webm"),
+    ("video/quicktime", "mov"),
+];
+
+#[derive(Clone)]
+pub struct YuriMedia {
+    client: reqwest::Client,
+    config: Arc<MediaConfig>,
+}
+
+#[derive(Debug)]
+pub struct Media {
+    pub file_name: String,
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct PreparedMedia {
+    pub original_path: String,
+    pub processed_path: String,
+    pub media: Media,
+}
+
+impl YuriMedia {
+    pub fn new(media_config: MediaConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .timeout(DOWNLOAD_TIMEOUT)
+                .build()?,
+            config: Arc::new(media_config),
+        })
+    }
+
+    /// Gets an image ready to be uploaded. Files that were already stored for
+    /// it are reused, so a retried post doesn't download or process it again,
+    /// unless the processed file was made for other limits.
+    pub async fn prepare(&self, image: &Image) -> anyhow::Result<PreparedMedia> {
+        let processed_stem = format!(
+            "{image_id}-{limits_key}",
+            image_id = image.image_id,
+            limits_key = limits_key(&self.config)
+        );
+
+        let processed_path = image.processed_path.as_ref().filter(|processed_path| {
+            Path::new(processed_path)
+                .file_stem()
+                .is_some_and(|file_stem| *file_stem == *processed_stem)
+        });
+        if let (Some(original_path), Some(processed_path)) = (&image.original_path, processed_path)
+        {
+            if let Ok(bytes) = tokio::fs::read(processed_path).await {
+                debug!("reusing the processed file at `{processed_path}`");
+
+                let extension = Path::new(processed_path)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or_default();
+                return Ok(PreparedMedia {
+                    original_path: original_path.clone(),
+                    processed_path: processed_path.clone(),
+                    media: Media {
+                        file_name: format!("image.{extension}"),
+                        mime_type: mime_type_for(extension).to_string(),
+                        bytes,
+                    },
+                });
+            }
+        }
+
+        let (original_path, original, mime_type) = self.get_original(image).await?;
+
+        let config = self.config.clone();
+        let media = tokio::task::spawn_blocking(move || {
+            normalize::normalize(&original, &mime_type, &config)
+        })
+        .await??;
+
+        let processed_path = self.config.storage_path.join("processed").join(format!(
+            "{processed_stem}.{extension}",
+            extension = extension_for(&media.mime_type)
+        ));
+        write_file(&processed_path, &media.bytes).await?;
+
+        Ok(PreparedMedia {
+            original_path,
+            processed_path: processed_path.to_string_lossy().into_owned(),
+            media,
+        })
+    }
+
+    async fn get_original(&self, image: &Image) -> anyhow::Result<(String, Vec<u8>, String)> {
+        if let Some(original_path) = &image.original_path {
+            if let Ok(bytes) = tokio::fs::read(original_path).await {
+                let extension = Path::new(original_path)
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .unwrap_or_default();
+                return Ok((
+                    original_path.clone(),
+                    bytes,
+                    mime_type_for(extension).to_string(),
+                ));
+            }
+        }
+
+        self.download_original(image).await
+    }
+
+    async fn download_original(&self, image: &Image) -> anyhow::Result<(String, Vec<u8>, String)> {
+        debug!(
+            "downloading media from `{image_path}`",
+            image_path = image.image_path
+        );
+
+        let response = self
+            .client
+            .get(&image.image_path)
+            .send()
+            .await?
+            .error_for_status()?;
+        let mime_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .and_then(|content_type| content_type.split(';').next())
+            .unwrap_or("application/octet-stream")
+            .trim()
+            .to_string();
+        let bytes = response.bytes().await?.to_vec();
+
+        let original_path = self.config.storage_path.join("originals").join(format!(
+            "{image_id}.{extension}",
+            image_id = image.image_id,
+            extension = extension_for(&mime_type)
+        ));
+        write_file(&original_path, &bytes).await?;
+
+        Ok((
+            original_path.to_string_lossy().into_owned(),
+            bytes,
+            mime_type,
+        ))
+    }
+
+    /// Downloads the originals of a submission's images that weren't stored
+    /// yet. Links to Discord attachments expire, so this is done while the
+    /// submission is fresh rather than when it's posted.
+    pub async fn store_originals(
+        &self,
+        database: &PgPool,
+        submission_id: i32,
+    ) -> anyhow::Result<()> {
+        for image in Image::get_submission_images(database, submission_id).await? {
+            if image.original_path.is_some() {
+                continue;
+            }
+
+            let (original_path, ..) = self.download_original(&image).await?;
+            Image::set_original_path(database, image.image_id, original_path).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores a local file as the original of an image, so it's never
+    /// downloaded.
+    pub async fn store_original(&self, image_id: i32, path: &Path) -> anyhow::Result<String> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+        let mime_type = mime_type_for(&extension);
+        if mime_type == "application/octet-stream" {
+            anyhow::bail!(
+                "`{path}` isn't a known image or video type",
+                path = path.display()
+            );
+        }
+
+        let bytes = tokio::fs::read(path).await?;
+        let original_path = self.config.storage_path.join("originals").join(format!(
+            "{image_id}.{extension}",
+            extension = extension_for(mime_type)
+        ));
+        write_file(&original_path, &bytes).await?;
+
+        Ok(original_path.to_string_lossy().into_owned())
+    }
+}
+
+/// Processes media again to fit tighter limits than the ones it was
+/// prepared for, for targets that don't accept as much as the instance.
+pub async fn fit(
+    media: &Media,
+    size_limit: u64,
+    matrix_limit: u64,
+    supported_mime_types: &[&str],
+) -> anyhow::Result<Media> {
+    let config = MediaConfig {
+        storage_path: std::path::PathBuf::new(),
+        image_size_limit: size_limit,
+        image_matrix_limit: matrix_limit,
+        video_size_limit: size_limit,
+        supported_mime_types: supported_mime_types
+            .iter()
+            .map(|mime_type| (*mime_type).to_string())
+            .collect(),
+    };
+    let (bytes, mime_type) = (media.bytes.clone(), media.mime_type.clone());
+
+    tokio::task::spawn_blocking(move || normalize::normalize(&bytes, &mime_type, &config)).await?
+}
+
+/// Tells apart the limits media was processed for, it's part of the
+/// processed file's name. FNV-1a is used since it stays the same between
+/// builds, unlike the standard library's hasher.
+fn limits_key(config: &MediaConfig) -> String {
+    let mut limits = format!(
+        "{image_size_limit}:{image_matrix_limit}:{video_size_limit}",
+        image_size_limit = config.image_size_limit,
+        image_matrix_limit = config.image_matrix_limit,
+        video_size_limit = config.video_size_limit
+    );
+    for mime_type in &config.supported_mime_types {
+        limits.push(':');
+        limits.push_str(mime_type);
+    }
+
+    let hash = limits
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+        });
+    format!("{hash:016x}")
+}
+
+fn extension_for(mime_type: &str) -> &'static str {
+    MEDIA_TYPES
+        .iter()
+        .find(|(media_type, _)| *media_type == mime_type)
+        .map_or("bin", |(_, extension)| extension)
+}
+
+pub fn mime_type_for(extension: &str) -> &'static str {
+    MEDIA_TYPES
+        .iter()
+        .find(|(_, media_extension)| *media_extension == extension)
+        .map_or("application/octet-stream", |(mime_type, _)| mime_type)
+}
+
+async fn write_file(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::write(path, bytes).await?;
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::{
+        gif::{GifDecoder, GifEncoder, Repeat},
+        jpeg::JpegEncoder,
+        png::PngDecoder,
+        webp::WebPDecoder,
+    },
+    imageops::FilterType,
+    AnimationDecoder, DynamicImage, Frame, ImageDecoder, ImageFormat, ImageReader,
+};
+
+use crate::config::MediaConfig;
+
+use super::{extension_for, Media};
+
+const INITIAL_JPEG_QUALITY: u8 = 90;
+const MIN_JPEG_QUALITY: u8 = 50;
+// How much an image is shrunk every time it still doesn't fit the size limit
+// after recompressing.
+const DOWNSCALE_STEP: f64 = 0.75;
+const MAX_ENCODE_ATTEMPTS: usize = 16;
+
+/// Turns a downloaded file into something the instance accepts. Images are
+/// decoded and encoded again, which leaves their metadata behind.
+pub fn normalize(original: &[u8], mime_type: &str, config: &MediaConfig) -> anyhow::Result<Media> {
+    let format = ImageReader::new(Cursor::new(original))
+        .with_guessed_format()?
+        .format();
+
+    match format {
+        Some(format) if format.reading_enabled() => {
+            if is_animated(original, format)? && is_supported("image/gif", config) {
+                normalize_animation(original, format, config)
+            } else {
+                normalize_image(original, format, config)
+            }
+        }
+        // Formats that can't be decoded here can only be passed along as they
+        // are. That's videos, but also AVIF and HEIC images, which `image`
+        // isn't built with decoders for, so they're never converted and only
+        // go through if the instance takes them.
+        _ => {
+            let size_limit = if mime_type.starts_with("video/") {
+                config.video_size_limit
+            } else {
+                config.image_size_limit
+            };
+
+            if !is_supported(mime_type, config) {
+                anyhow::bail!(
+                    "`{mime_type}` media isn't supported by the instance and can't be converted"
+                );
+            }
+            if original.len() as u64 > size_limit {
+                anyhow::bail!(
+                    "`{mime_type}` media is {size} bytes, over the limit of {size_limit} bytes",
+                    size = original.len()
+                );
+            }
+
+            warn!("`{mime_type}` media can't be processed, uploading it with its metadata");
+            Ok(Media {
+                file_name: format!("media.{extension}", extension = extension_for(mime_type)),
+                mime_type: mime_type.to_string(),
+                bytes: original.to_vec(),
+            })
+        }
+    }
+}
+
+fn is_supported(mime_type: &str, config: &MediaConfig) -> bool {
+    config
+        .supported_mime_types
+        .iter()
+        .any(|supported_mime_type| supported_mime_type == mime_type)
+}
+
+fn is_animated(original: &[u8], format: ImageFormat) -> anyhow::Result<bool> {
+    Ok(match format {
+        ImageFormat::Gif => {
+            GifDecoder::new(Cursor::new(original))?
+                .into_frames()
+                .take(2)
+                .count()
+                > 1
+        }
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(original))?.has_animation(),
+        ImageFormat::Png => PngDecoder::new(Cursor::new(original))?.is_apng()?,
+        _ => false,
+    })
+}
+
+fn normalize_image(
+    original: &[u8],
+    format: ImageFormat,
+    config: &MediaConfig,
+) -> anyhow::Result<Media> {
+    let mut decoder = ImageReader::with_format(Cursor::new(original), format).into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    // The orientation is part of the metadata that gets stripped, so it has to
+    // be baked into the pixels first.
+    image.apply_orientation(orientation);
+
+    let transparent =
+        image.color().has_alpha() && image.to_rgba8().pixels().any(|pixel| pixel[3] < u8::MAX);
+
+    let (mut width, mut height) = fit_matrix_limit(image.width(), image.height(), config);
+    let mut quality = INITIAL_JPEG_QUALITY;
+    for _ in 0..MAX_ENCODE_ATTEMPTS {
+        let resized = if (width, height) == (image.width(), image.height()) {
+            image.clone()
+        } else {
+            image.resize_exact(width, height, FilterType::Lanczos3)
+        };
+
+        let mut bytes = Cursor::new(vec![]);
+        if transparent {
+            resized.write_to(&mut bytes, ImageFormat::Png)?;
+        } else {
+            resized
+                .to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality))?;
+        }
+        let bytes = bytes.into_inner();
+
+        if bytes.len() as u64 <= config.image_size_limit {
+            let mime_type = if transparent {
+                "image/png"
+            } else {
+                "image/jpeg"
+            };
+            debug!(
+                "normalized an image from {original_size} to {size} bytes at {width}x{height}",
+                original_size = original.len(),
+                size = bytes.len()
+            );
+
+            return Ok(Media {
+                file_name: format!("image.{extension}", extension = extension_for(mime_type)),
+                mime_type: mime_type.to_string(),
+                bytes,
+            });
+        }
+
+        // Recompressing loses less than downscaling, so that's tried first.
+        if !transparent && quality > MIN_JPEG_QUALITY {
+            quality -= 10;
+        } else {
+            (width, height) = downscale(width, height);
+        }
+    }
+
+    anyhow::bail!(
+        "couldn't fit the image in {size_limit} bytes",
+        size_limit = config.image_size_limit
+    )
+}
+
+fn normalize_animation(
+    original: &[u8],
+    format: ImageFormat,
+    config: &MediaConfig,
+) -> anyhow::Result<Media> {
+    let frames = match format {
+        ImageFormat::Gif => GifDecoder::new(Cursor::new(original))?
+            .into_frames()
+            .collect_frames()?,
+        ImageFormat::WebP => WebPDecoder::new(Cursor::new(original))?
+            .into_frames()
+            .collect_frames()?,
+        ImageFormat::Png => PngDecoder::new(Cursor::new(original))?
+            .apng()?
+            .into_frames()
+            .collect_frames()?,
+        _ => anyhow::bail!("`{format:?}` images can't be animated"),
+    };
+    let Some(first_frame) = frames.first() else {
+        anyhow::bail!("animation has no frames");
+    };
+
+    let (original_width, original_height) = first_frame.buffer().dimensions();
+    let (mut width, mut height) = fit_matrix_limit(original_width, original_height, config);
+    for _ in 0..MAX_ENCODE_ATTEMPTS {
+        let mut bytes = vec![];
+        {
+            let mut encoder = GifEncoder::new(&mut bytes);
+            encoder.set_repeat(Repeat::Infinite)?;
+            encoder.encode_frames(frames.iter().map(|frame| {
+                if (width, height) == (original_width, original_height) {
+                    frame.clone()
+                } else {
+                    Frame::from_parts(
+                        image::imageops::resize(
+                            frame.buffer(),
+                            width,
+                            height,
+                            FilterType::Triangle,
+                        ),
+                        0,
+                        0,
+                        frame.delay(),
+                    )
+                }
+            }))?;
+        }
+
+        if bytes.len() as u64 <= config.image_size_limit {
+            debug!(
+                "normalized an animation from {original_size} to {size} bytes at {width}x{height}",
+                original_size = original.len(),
+                size = bytes.len()
+            );
+
+            return Ok(Media {
+                file_name: String::from("animation.gif"),
+                mime_type: String::from("image/gif"),
+                bytes,
+            });
+        }
+
+        (width, height) = downscale(width, height);
+    }
+
+    anyhow::bail!(
+        "couldn't fit the animation in {size_limit} bytes",
+        size_limit = config.image_size_limit
+    )
+}
+
+fn fit_matrix_limit(width: u32, height: u32, config: &MediaConfig) -> (u32, u32) {
+    let pixels = u64::from(width) * u64::from(height);
+    if pixels <= config.image_matrix_limit {
+        return (width, height);
+    }
+
+    let scale = (config.image_matrix_limit as f64 / pixels as f64).sqrt();
+    scale_dimensions(width, height, scale)
+}
+
+fn downscale(width: u32, height: u32) -> (u32, u32) {
+    scale_dimensions(width, height, DOWNSCALE_STEP)
+}
+
+fn scale_dimensions(width: u32, height: u32, scale: f64) -> (u32, u32) {
+    (
+        ((f64::from(width) * scale).floor() as u32).max(1),
+        ((f64::from(height) * scale).floor() as u32).max(1),
+    )
+}
@@ -0,0 +1,145 @@
+use sqlx::{
+    migrate::{Migrate, Migrator},
+    PgPool,
+};
+
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the migration was changed since.
+    Changed,
+    /// Applied, but the migration isn't known to this version of the bot.
+    Unknown,
+}
+
+/// Every known and applied migration, oldest first.
+pub async fn get_migration_status(pool: &PgPool) -> anyhow::Result<Vec<MigrationStatus>> {
+    let mut connection = pool.acquire().await?;
+    connection.ensure_migrations_table().await?;
+
+    if let Some(version) = connection.dirty_version().await? {
+        anyhow::bail!("migration {version} was interrupted and has to be fixed manually");
+    }
+    let applied_migrations = connection.list_applied_migrations().await?;
+
+    let mut migration_status = MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| MigrationStatus {
+            version: migration.version,
+            description: migration.description.to_string(),
+            state: match applied_migrations
+                .iter()
+                .find(|applied_migration| applied_migration.version == migration.version)
+            {
+                Some(applied_migration) if applied_migration.checksum == migration.checksum => {
+                    MigrationState::Applied
+                }
+                Some(_) => MigrationState::Changed,
+                None => MigrationState::Pending,
+            },
+        })
+        .collect::<Vec<_>>();
+
+    for applied_migration in &applied_migrations {
+        if !migration_status
+            .iter()
+            .any(|migration| migration.version == applied_migration.version)
+        {
+            migration_status.push(MigrationStatus {
+                version: applied_migration.version,
+                description: String::new(),
+                state: MigrationState::Unknown,
+            });
+        }
+    }
+    migration_status.sort_unstable_by_key(|migration| migration.version);
+
+    Ok(migration_status)
+}
+
+/// Fails unless every known migration is applied as it is, for commands
+/// that only read the database and so shouldn't change its schema.
+pub async fn ensure_migrated(pool: &PgPool) -> anyhow::Result<()> {
+    let mismatched_migrations = get_migration_status(pool)
+        .await?
+        .into_iter()
+        .filter(|migration| migration.state != MigrationState::Applied)
+        .map(|migration| {
+            format!(
+                "{version} ({state:?})",
+                version = migration.version,
+                state = migration.state
+            )
+        })
+        .collect::<Vec<_>>();
+
+    if !mismatched_migrations.is_empty() {
+        anyhow::bail!(
+            "the database schema doesn't match this version of the bot, see `migrate status` for migrations {migrations}",
+            migrations = mismatched_migrations.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Reverts migrations newer than `target`, or only the latest one if there's
+/// no target. Returns how many were reverted.
+///
+/// Migrations this version of the bot doesn't know can't be reverted by it,
+/// so nothing is reverted while there are any.
+pub async fn revert_migrations(pool: &PgPool, target: Option<i64>) -> anyhow::Result<usize> {
+    let migration_status = get_migration_status(pool).await?;
+
+    let unknown_versions = migration_status
+        .iter()
+        .filter(|migration| migration.state == MigrationState::Unknown)
+        .map(|migration| migration.version.to_string())
+        .collect::<Vec<_>>();
+    if !unknown_versions.is_empty() {
+        anyhow::bail!(
+            "migrations {versions} were applied by a newer version of the bot, revert them with that version first",
+            versions = unknown_versions.join(", ")
+        );
+    }
+
+    let applied_versions = migration_status
+        .into_iter()
+        .filter(|migration| migration.state != MigrationState::Pending)
+        .map(|migration| migration.version)
+        .collect::<Vec<_>>();
+
+    let target = match target {
+        Some(target) => target,
+        None => match applied_versions.as_slice() {
+            [] => return Ok(0),
+            [.., previous, _] => *previous,
+            [_] => 0,
+        },
+    };
+
+    // Only the versions with a down migration are undone.
+    let reverted_migrations = MIGRATOR
+        .iter()
+        .filter(|migration| {
+            migration.migration_type.is_down_migration()
+                && migration.version > target
+                && applied_versions.contains(&migration.version)
+        })
+        .count();
+
+    MIGRATOR.undo(pool, target).await?;
+
+    Ok(reverted_migrations)
+}
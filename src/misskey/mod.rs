@@ -0,0 +1,491 @@
+use std::time::Duration;
+
+use reqwest::{
+    multipart::{Form, Part},
+    Client,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{MisskeyConfig, MisskeyVisibility},
+    media::Media,
+    publisher::{Publication, Published, Publisher, RetryPolicy},
+};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct YuriMisskey {
+    client: Client,
+    retry_policy: RetryPolicy,
+    instance_host: String,
+    access_token: String,
+    visibility: MisskeyVisibility,
+    repost_visibility: MisskeyVisibility,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DriveFile {
+    pub id: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Note {
+    pub id: String,
+    #[serde(default)]
+    pub file_ids: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatedNote {
+    created_note: Note,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: String,
+}
+
+// Misskey takes the access token as the `i` parameter of every request, which
+// older forks need even where the `Authorization` header works.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateNote<'a> {
+    i: &'a str,
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cw: Option<String>,
+    pub visibility: MisskeyVisibility,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_ids: Vec<String>,
+}
+
+impl YuriMisskey {
+    pub fn new(misskey_config: MisskeyConfig) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .user_agent(concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "/",
+                    env!("CARGO_PKG_VERSION")
+                ))
+                .timeout(REQUEST_TIMEOUT)
+                .build()?,
+            retry_policy: RetryPolicy::new(&misskey_config.retry),
+            instance_host: misskey_config.instance_host,
+            access_token: misskey_config.access_token,
+            visibility: misskey_config.visibility,
+            repost_visibility: misskey_config.repost_visibility,
+        })
+    }
+
+    pub async fn create_drive_file(
+        &self,
+        media: &Media,
+        comment: Option<&str>,
+    ) -> anyhow::Result<DriveFile> {
+        debug!(
+            "uploading a `{mime_type}` drive file",
+            mime_type = media.mime_type
+        );
+
+        // Fail early on a bad MIME type instead of on every retry.
+        Part::bytes(vec![]).mime_str(&media.mime_type)?;
+
+        let drive_file = self
+            .retry_policy
+            .send(|| {
+                let form = Form::new()
+                    .text("i", self.access_token.clone())
+                    .text("name", media.file_name.clone())
+                    .part(
+                        "file",
+                        Part::bytes(media.bytes.clone())
+                            .file_name(media.file_name.clone())
+                            .mime_str(&media.mime_type)
+                            .expect("MIME type was validated before"),
+                    );
+                let form = match comment {
+                    Some(comment) => form.text("comment", comment.to_string()),
+                    None => form,
+                };
+
+                self.client
+                    .post(self.endpoint("drive/files/create"))
+                    .multipart(form)
+                    .send()
+            })
+            .await?
+            .json::<DriveFile>()
+            .await?;
+
+        debug!(
+            "uploaded a drive file with: `file_id`: {file_id}",
+            file_id = drive_file.id
+        );
+        Ok(drive_file)
+    }
+
+    pub async fn mark_drive_file_sensitive(&self, file_id: &str) -> anyhow::Result<()> {
+        debug!("marking a drive file as sensitive with: `file_id`: {file_id}");
+
+        self.retry_policy
+            .send(|| {
+                self.client
+                    .post(self.endpoint("drive/files/update"))
+                    .json(&serde_json::json!({
+                        "i": self.access_token,
+                        "fileId": file_id,
+                        "isSensitive": true,
+                    }))
+                    .send()
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn create_note(
+        &self,
+        create_note: &CreateNote<'_>,
+        idempotency_key: uuid::Uuid,
+    ) -> anyhow::Result<Note> {
+        debug!("creating a note with: `idempotency_key`: {idempotency_key}");
+
+        let created_note = self
+            .retry_policy
+            .send(|| {
+                self.client
+                    .post(self.endpoint("notes/create"))
+                    .header("Idempotency-Key", idempotency_key.to_string())
+                    .json(create_note)
+                    .send()
+            })
+            .await?
+            .json::<CreatedNote>()
+            .await?;
+
+        debug!(
+            "created a note with: `note_id`: {note_id}",
+            note_id = created_note.created_note.id
+        );
+        Ok(created_note.created_note)
+    }
+
+    pub async fn get_own_notes(&self) -> anyhow::Result<Vec<Note>> {
+        debug!("getting own recent notes");
+
+        let user = self
+            .retry_policy
+            .send(|| {
+                self.client
+                    .post(self.endpoint("i"))
+                    .json(&serde_json::json!({ "i": self.access_token }))
+                    .send()
+            })
+            .await?
+            .json::<User>()
+            .await?;
+
+        let notes = self
+            .retry_policy
+            .send(|| {
+                self.client
+                    .post(self.endpoint("users/notes"))
+                    .json(&serde_json::json!({
+                        "i": self.access_token,
+                        "userId": user.id,
+                        "withFiles": true,
+                        "limit": 40,
+                    }))
+                    .send()
+            })
+            .await?
+            .json::<Vec<Note>>()
+            .await?;
+
+        Ok(notes)
+    }
+
+    fn endpoint(&self, path: &str) -> String {
+        if self.instance_host.starts_with("http://") || self.instance_host.starts_with("https://") {
+            format!("{host}/api/{path}", host = self.instance_host)
+        } else {
+            format!("https://{host}/api/{path}", host = self.instance_host)
+        }
+    }
+
+    fn note_url(&self, note_id: &str) -> String {
+        if self.instance_host.starts_with("http://") || self.instance_host.starts_with("https://") {
+            format!("{host}/notes/{note_id}", host = self.instance_host)
+        } else {
+            format!("https://{host}/notes/{note_id}", host = self.instance_host)
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Publisher for YuriMisskey {
+    fn target(&self) -> &'static str {
+        "misskey"
+    }
+
+    async fn upload_media(
+        &self,
+        media: &Media,
+        description: Option<&str>,
+    ) -> anyhow::Result<String> {
+        Ok(self.create_drive_file(media, description).await?.id)
+    }
+
+    async fn publish(
+        &self,
+        publication: &Publication,
+        idempotency_key: uuid::Uuid,
+    ) -> anyhow::Result<Published> {
+        // Misskey marks files as sensitive rather than notes.
+        if publication.sensitive {
+            for file_id in &publication.media_ids {
+                self.mark_drive_file_sensitive(file_id).await?;
+            }
+        }
+
+        let note = self
+            .create_note(
+                &CreateNote {
+                    i: &self.access_token,
+                    text: publication.text.clone(),
+                    cw: publication.content_warning.clone(),
+                    visibility: if publication.repost {
+                        self.repost_visibility
+                    } else {
+                        self.visibility
+                    },
+                    file_ids: publication.media_ids.clone(),
+                },
+                idempotency_key,
+            )
+            .await?;
+
+        Ok(Published {
+            url: Some(self.note_url(&note.id)),
+            id: note.id,
+        })
+    }
+
+    async fn find_published(&self, media_ids: &[String]) -> anyhow::Result<Option<Published>> {
+        if media_ids.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(self
+            .get_own_notes()
+            .await?
+            .into_iter()
+            .find(|note| {
+                note.file_ids
+                    .iter()
+                    .any(|file_id| media_ids.contains(file_id))
+            })
+            .map(|note| Published {
+                url: Some(self.note_url(&note.id)),
+                id: note.id,
+            }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        body::Bytes,
+        extract::{Path, State},
+        http::HeaderMap,
+        routing::post,
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::config::RetryConfig;
+
+    /// A request the mock instance got, with its body as text.
+    struct Request {
+        endpoint: String,
+        headers: HeaderMap,
+        body: String,
+    }
+
+    type Requests = Arc<Mutex<Vec<Request>>>;
+
+    async fn api(
+        State(requests): State<Requests>,
+        Path(endpoint): Path<String>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Json<Value> {
+        let response = match endpoint.as_str() {
+            "drive/files/create" => json!({ "id": "file-1" }),
+            "drive/files/update" => json!({ "id": "file-1", "isSensitive": true }),
+            "notes/create" => json!({ "createdNote": { "id": "note-1", "fileIds": ["file-1"] } }),
+            _ => json!({}),
+        };
+
+        requests.lock().unwrap().push(Request {
+            endpoint,
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        });
+
+        Json(response)
+    }
+
+    async fn mock_misskey() -> (YuriMisskey, Requests) {
+        let requests = Requests::default();
+        let app = Router::new()
+            .route("/api/*endpoint", post(api))
+            .with_state(Arc::clone(&requests));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let misskey = YuriMisskey::new(MisskeyConfig {
+            instance_host: format!("http://{address}"),
+            access_token: "access-token".to_string(),
+            visibility: MisskeyVisibility::Public,
+            repost_visibility: MisskeyVisibility::Home,
+            retry: RetryConfig {
+                max_attempts: 1,
+                base_delay_ms: 1,
+                max_delay_secs: 1,
+            },
+        })
+        .unwrap();
+
+        (misskey, requests)
+    }
+
+    fn publication(sensitive: bool, repost: bool) -> Publication {
+        Publication {
+            text: "Art by someone\nhttps://example.com/art".to_string(),
+            source_link: Some("https://example.com/art".to_string()),
+            media_ids: vec!["file-1".to_string()],
+            content_warning: Some("Spoilers".to_string()),
+            sensitive,
+            repost,
+        }
+    }
+
+    #[tokio::test]
+    async fn uploads_a_drive_file_with_its_alt_text() {
+        let (misskey, requests) = mock_misskey().await;
+
+        let file_id = misskey
+            .upload_media(
+                &Media {
+                    file_name: "art.png".to_string(),
+                    mime_type: "image/png".to_string(),
+                    bytes: b"not really a png".to_vec(),
+                },
+                Some("Two girls holding hands"),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(file_id, "file-1");
+
+        let requests = requests.lock().unwrap();
+        let [request] = requests.as_slice() else {
+            panic!("expected one request");
+        };
+        assert_eq!(request.endpoint, "drive/files/create");
+        assert!(request.headers["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("multipart/form-data"));
+        for expected in [
+            "name=\"i\"\r\n\r\naccess-token",
+            "name=\"comment\"\r\n\r\nTwo girls holding hands",
+            "filename=\"art.png\"",
+            "Content-Type: image/png",
+            "not really a png",
+        ] {
+            assert!(request.body.contains(expected), "missing `{expected}`");
+        }
+    }
+
+    #[tokio::test]
+    async fn marks_files_of_sensitive_posts_before_the_note() {
+        let (misskey, requests) = mock_misskey().await;
+
+        misskey
+            .publish(&publication(true, false), uuid::Uuid::new_v4())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let [update, create] = requests.as_slice() else {
+            panic!("expected two requests");
+        };
+        assert_eq!(update.endpoint, "drive/files/update");
+        assert_eq!(
+            serde_json::from_str::<Value>(&update.body).unwrap(),
+            json!({ "i": "access-token", "fileId": "file-1", "isSensitive": true })
+        );
+        assert_eq!(create.endpoint, "notes/create");
+    }
+
+    #[tokio::test]
+    async fn creates_a_note_from_the_publication() {
+        let (misskey, requests) = mock_misskey().await;
+        let idempotency_key = uuid::Uuid::new_v4();
+
+        let published = misskey
+            .publish(&publication(false, false), idempotency_key)
+            .await
+            .unwrap();
+
+        assert_eq!(published.id, "note-1");
+        assert_eq!(
+            published.url.as_deref(),
+            Some(misskey.note_url("note-1").as_str())
+        );
+
+        let requests = requests.lock().unwrap();
+        let [request] = requests.as_slice() else {
+            panic!("expected one request");
+        };
+        assert_eq!(request.endpoint, "notes/create");
+        assert_eq!(
+            request.headers["idempotency-key"],
+            idempotency_key.to_string()
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&request.body).unwrap(),
+            json!({
+                "i": "access-token",
+                "text": "Art by someone\nhttps://example.com/art",
+                "cw": "Spoilers",
+                "visibility": "public",
+                "fileIds": ["file-1"],
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn reposts_use_the_repost_visibility() {
+        let (misskey, requests) = mock_misskey().await;
+
+        misskey
+            .publish(&publication(false, true), uuid::Uuid::new_v4())
+            .await
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        let body = serde_json::from_str::<Value>(&requests[0].body).unwrap();
+        assert_eq!(body["visibility"], "home");
+    }
+}
@@ -0,0 +1,186 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "audit_action", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    Approve,
+    Reject,
+    EditAltText,
+    EditImages,
+    PausePosting,
+    ResumePosting,
+    Expire,
+    ImportBacklog,
+}
+
+/// Entries can only be added, the table refuses changes to them.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub audit_id: i32,
+    pub actor_id: Option<i64>,
+    pub action: AuditAction,
+    pub submission_id: Option<i32>,
+
+    pub before_value: Option<String>,
+    pub after_value: Option<String>,
+
+    pub audit_date: chrono::NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct AddAuditEntry {
+    pub actor_id: Option<u64>,
+    pub action: AuditAction,
+    pub submission_id: Option<i32>,
+    pub before_value: Option<String>,
+    pub after_value: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AuditFilter {
+    pub submission_id: Option<i32>,
+    pub actor_id: Option<u64>,
+    pub action: Option<AuditAction>,
+}
+
+pub trait AuditEntryHelpers {
+    async fn add_audit_entry(
+        executor: impl PgExecutor,
+        add_audit_entry: AddAuditEntry,
+    ) -> anyhow::Result<AuditEntry>;
+
+    async fn search_audit_log(
+        executor: impl PgExecutor,
+        audit_filter: AuditFilter,
+        limit: i64,
+    ) -> anyhow::Result<Vec<AuditEntry>>;
+
+    async fn get_decision_entry(
+        executor: impl PgExecutor,
+        submission_id: i32,
+    ) -> anyhow::Result<Option<AuditEntry>>;
+
+    async fn get_all_audit_entries(executor: impl PgExecutor) -> anyhow::Result<Vec<AuditEntry>>;
+
+    async fn import_audit_entry(
+        executor: impl PgExecutor,
+        audit_entry: AuditEntry,
+    ) -> anyhow::Result<bool>;
+}
+
+impl AuditEntryHelpers for AuditEntry {
+    async fn add_audit_entry(
+        executor: impl PgExecutor<'_>,
+        add_audit_entry: AddAuditEntry,
+    ) -> anyhow::Result<Self> {
+        debug!("adding a new audit entry");
+
+        let added_entry = sqlx::query_as!(
+            AuditEntry,
+            r#"INSERT INTO audit_log (actor_id, action, submission_id, before_value, after_value) VALUES ($1, $2, $3, $4, $5)
+            RETURNING audit_id, actor_id, action as "action: AuditAction", submission_id, before_value, after_value, audit_date"#,
+            add_audit_entry.actor_id.map(i64::try_from).transpose()?,
+            add_audit_entry.action as AuditAction,
+            add_audit_entry.submission_id,
+            add_audit_entry.before_value,
+            add_audit_entry.after_value
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "added a new audit entry with: `audit_id`: {audit_id}, `action`: {action:?}",
+            audit_id = added_entry.audit_id,
+            action = added_entry.action
+        );
+        Ok(added_entry)
+    }
+
+    /// Gets the latest entries matching every filter that's set.
+    async fn search_audit_log(
+        executor: impl PgExecutor<'_>,
+        audit_filter: AuditFilter,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        let audit_entries = sqlx::query_as!(
+            AuditEntry,
+            r#"SELECT audit_id, actor_id, action as "action: AuditAction", submission_id, before_value, after_value, audit_date
+            FROM audit_log
+            WHERE ($1::INTEGER IS NULL OR submission_id = $1)
+            AND ($2::BIGINT IS NULL OR actor_id = $2)
+            AND ($3::audit_action IS NULL OR action = $3)
+            ORDER BY audit_id DESC LIMIT $4"#,
+            audit_filter.submission_id,
+            audit_filter.actor_id.map(i64::try_from).transpose()?,
+            audit_filter.action as Option<AuditAction>,
+            limit
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(audit_entries)
+    }
+
+    /// Gets the entry of whoever decided on a submission, if it was decided
+    /// on through Discord.
+    async fn get_decision_entry(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+    ) -> anyhow::Result<Option<Self>> {
+        let decision_entry = sqlx::query_as!(
+            AuditEntry,
+            r#"SELECT audit_id, actor_id, action as "action: AuditAction", submission_id, before_value, after_value, audit_date
+            FROM audit_log
+            WHERE submission_id = $1 AND action IN ('approve', 'reject')
+            ORDER BY audit_id DESC LIMIT 1"#,
+            submission_id
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(decision_entry)
+    }
+
+    async fn get_all_audit_entries(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let audit_entries = sqlx::query_as!(
+            AuditEntry,
+            r#"SELECT audit_id, actor_id, action as "action: AuditAction", submission_id, before_value, after_value, audit_date
+            FROM audit_log ORDER BY audit_id"#
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(audit_entries)
+    }
+
+    /// Adds an entry from an archive, unless the same action on the same
+    /// submission at the same date is already there. `submission_id` has to
+    /// be the one in this database.
+    async fn import_audit_entry(
+        executor: impl PgExecutor<'_>,
+        audit_entry: AuditEntry,
+    ) -> anyhow::Result<bool> {
+        let inserted = sqlx::query!(
+            r#"INSERT INTO audit_log (actor_id, action, submission_id, before_value, after_value, audit_date)
+            SELECT $1, $2, $3, $4, $5, $6 WHERE NOT EXISTS (
+                SELECT FROM audit_log
+                WHERE actor_id IS NOT DISTINCT FROM $1 AND action = $2
+                AND submission_id IS NOT DISTINCT FROM $3 AND audit_date = $6
+            )"#,
+            audit_entry.actor_id,
+            audit_entry.action as AuditAction,
+            audit_entry.submission_id,
+            audit_entry.before_value,
+            audit_entry.after_value,
+            audit_entry.audit_date
+        )
+        .execute(executor)
+        .await?
+        .rows_affected()
+            > 0;
+
+        Ok(inserted)
+    }
+}
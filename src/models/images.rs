@@ -0,0 +1,273 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+use super::ImportedRow;
+
+/// Mastodon doesn't allow attaching more media than this to a single status.
+pub const MAX_SUBMISSION_IMAGES: usize = 4;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Image {
+    pub image_id: i32,
+    pub submission_id: i32,
+    /// Only set on images from when they were attached to votes.
+    pub vote_id: Option<i32>,
+
+    pub position: i16,
+    pub image_path: String,
+    pub alt_text: Option<String>,
+
+    /// Where the file was stored as it was downloaded, and after it was
+    /// normalized for uploading.
+    pub original_path: Option<String>,
+    pub processed_path: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AddImages {
+    pub submission_id: i32,
+    pub image_paths: Vec<String>,
+    /// Goes with the image in the same position, images past its end have
+    /// none.
+    pub alt_texts: Vec<Option<String>>,
+}
+
+#[derive(Debug)]
+pub struct UpdateImageFiles {
+    pub original_path: String,
+    pub processed_path: String,
+}
+
+pub trait ImageHelpers {
+    async fn add_images(
+        executor: impl PgExecutor,
+        add_images: AddImages,
+    ) -> anyhow::Result<Vec<Image>>;
+
+    async fn get_submission_images(
+        executor: impl PgExecutor,
+        submission_id: i32,
+    ) -> anyhow::Result<Vec<Image>>;
+
+    async fn set_image_order(
+        executor: impl PgExecutor,
+        submission_id: i32,
+        image_ids: Vec<i32>,
+    ) -> anyhow::Result<Vec<Image>>;
+
+    async fn update_alt_text(
+        executor: impl PgExecutor,
+        image_id: i32,
+        alt_text: Option<String>,
+    ) -> anyhow::Result<Image>;
+
+    async fn update_image_files(
+        executor: impl PgExecutor,
+        image_id: i32,
+        image_files: UpdateImageFiles,
+    ) -> anyhow::Result<Image>;
+
+    async fn set_original_path(
+        executor: impl PgExecutor,
+        image_id: i32,
+        original_path: String,
+    ) -> anyhow::Result<Image>;
+
+    async fn get_all_images(executor: impl PgExecutor) -> anyhow::Result<Vec<Image>>;
+
+    async fn import_image(executor: impl PgExecutor, image: Image) -> anyhow::Result<ImportedRow>;
+}
+
+impl ImageHelpers for Image {
+    async fn add_images(
+        executor: impl PgExecutor<'_>,
+        add_images: AddImages,
+    ) -> anyhow::Result<Vec<Self>> {
+        debug!("adding new images");
+
+        let mut added_images = sqlx::query_as!(
+            Image,
+            r#"INSERT INTO images (submission_id, position, image_path, alt_text)
+            SELECT $1, (ordinality - 1)::SMALLINT, image_path, alt_text FROM UNNEST($2::TEXT[], $3::TEXT[]) WITH ORDINALITY AS paths(image_path, alt_text, ordinality)
+            WHERE image_path IS NOT NULL
+            RETURNING image_id, submission_id, vote_id, position, image_path, alt_text, original_path, processed_path"#,
+            add_images.submission_id,
+            &add_images.image_paths,
+            &add_images.alt_texts as &[Option<String>]
+        )
+        .fetch_all(executor)
+        .await?;
+        added_images.sort_by_key(|image| image.position);
+
+        debug!(
+            "added {count} new images with: `submission_id`: {submission_id}",
+            count = added_images.len(),
+            submission_id = add_images.submission_id
+        );
+        Ok(added_images)
+    }
+
+    async fn get_submission_images(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let submission_images = sqlx::query_as!(
+            Image,
+            "SELECT image_id, submission_id, vote_id, position, image_path, alt_text, original_path, processed_path FROM images WHERE submission_id = $1 ORDER BY position",
+            submission_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(submission_images)
+    }
+
+    /// Puts the images of a submission in the order of `image_ids`, removing
+    /// the ones that aren't in it.
+    async fn set_image_order(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+        image_ids: Vec<i32>,
+    ) -> anyhow::Result<Vec<Self>> {
+        debug!("reordering images");
+
+        let mut ordered_images = sqlx::query_as!(
+            Image,
+            r#"WITH kept_images AS (
+                SELECT image_id, (ordinality - 1)::SMALLINT AS position
+                FROM UNNEST($2::INTEGER[]) WITH ORDINALITY AS ids(image_id, ordinality)
+            ), removed_images AS (
+                DELETE FROM images WHERE submission_id = $1 AND image_id <> ALL($2)
+            )
+            UPDATE images SET position = kept_images.position FROM kept_images
+            WHERE images.image_id = kept_images.image_id AND images.submission_id = $1
+            RETURNING images.image_id, images.submission_id, images.vote_id, images.position as "position!", images.image_path, images.alt_text, images.original_path, images.processed_path"#,
+            submission_id,
+            &image_ids
+        )
+        .fetch_all(executor)
+        .await?;
+        ordered_images.sort_by_key(|image| image.position);
+
+        debug!(
+            "reordered images with: `submission_id`: {submission_id}, `image_ids`: {image_ids:?}",
+        );
+        Ok(ordered_images)
+    }
+
+    async fn update_alt_text(
+        executor: impl PgExecutor<'_>,
+        image_id: i32,
+        alt_text: Option<String>,
+    ) -> anyhow::Result<Self> {
+        debug!("updating the alt text of an image");
+
+        let updated_image = sqlx::query_as!(
+            Image,
+            r#"UPDATE images SET alt_text = $2 WHERE image_id = $1
+            RETURNING image_id, submission_id, vote_id, position, image_path, alt_text, original_path, processed_path"#,
+            image_id,
+            alt_text
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "updated the alt text of an image with: `image_id`: {image_id}",
+            image_id = updated_image.image_id
+        );
+        Ok(updated_image)
+    }
+
+    async fn update_image_files(
+        executor: impl PgExecutor<'_>,
+        image_id: i32,
+        image_files: UpdateImageFiles,
+    ) -> anyhow::Result<Self> {
+        debug!("updating the files of an image");
+
+        let updated_image = sqlx::query_as!(
+            Image,
+            r#"UPDATE images SET original_path = $2, processed_path = $3 WHERE image_id = $1
+            RETURNING image_id, submission_id, vote_id, position, image_path, alt_text, original_path, processed_path"#,
+            image_id,
+            image_files.original_path,
+            image_files.processed_path
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "updated the files of an image with: `image_id`: {image_id}",
+            image_id = updated_image.image_id
+        );
+        Ok(updated_image)
+    }
+
+    async fn set_original_path(
+        executor: impl PgExecutor<'_>,
+        image_id: i32,
+        original_path: String,
+    ) -> anyhow::Result<Self> {
+        debug!("setting the original file of an image");
+
+        let updated_image = sqlx::query_as!(
+            Image,
+            r#"UPDATE images SET original_path = $2 WHERE image_id = $1
+            RETURNING image_id, submission_id, vote_id, position, image_path, alt_text, original_path, processed_path"#,
+            image_id,
+            original_path
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "set the original file of an image with: `image_id`: {image_id}",
+            image_id = updated_image.image_id
+        );
+        Ok(updated_image)
+    }
+
+    async fn get_all_images(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let images = sqlx::query_as!(
+            Image,
+            "SELECT image_id, submission_id, vote_id, position, image_path, alt_text, original_path, processed_path FROM images ORDER BY image_id"
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(images)
+    }
+
+    /// Inserts an image from an archive, unless the submission already has
+    /// an image in its position. `submission_id` and `vote_id` have to be the
+    /// ones in this database.
+    async fn import_image(
+        executor: impl PgExecutor<'_>,
+        image: Image,
+    ) -> anyhow::Result<ImportedRow> {
+        let imported_image = sqlx::query_as!(
+            ImportedRow,
+            r#"WITH existing_image AS (
+                SELECT image_id FROM images WHERE submission_id = $1 AND position = $2
+            ), inserted_image AS (
+                INSERT INTO images (submission_id, position, vote_id, image_path, alt_text, original_path, processed_path)
+                SELECT $1, $2, $3, $4, $5, $6, $7 WHERE NOT EXISTS (SELECT FROM existing_image)
+                RETURNING image_id
+            )
+            SELECT image_id as "id!", false as "inserted!" FROM existing_image
+            UNION ALL SELECT image_id, true FROM inserted_image"#,
+            image.submission_id,
+            image.position,
+            image.vote_id,
+            image.image_path,
+            image.alt_text,
+            image.original_path,
+            image.processed_path
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(imported_image)
+    }
+}
@@ -1,2 +1,17 @@
+pub mod audit_log;
+pub mod images;
 pub mod pending_approvals;
+pub mod posts;
+pub mod reposts;
+pub mod reviewer_votes;
+pub mod scheduler_state;
 pub mod submissions;
+pub mod votes;
+
+/// A row that was imported from an archive, with its ID in this database and
+/// whether it was inserted or was already there from an earlier import.
+#[derive(Debug)]
+pub struct ImportedRow {
+    pub id: i32,
+    pub inserted: bool,
+}
@@ -1,6 +1,7 @@
+use serde::{Deserialize, Serialize};
 use sqlx::PgExecutor;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PendingApproval {
     pub submission_id: i32,
     pub message_id: i64,
@@ -27,11 +28,30 @@ pub trait PendingApprovalHelpers {
     async fn remove_pending_approval(
         executor: impl PgExecutor,
         remove_pending_approval: RemovePendingApproval,
-    ) -> anyhow::Result<PendingApproval>;
+    ) -> anyhow::Result<Option<PendingApproval>>;
 
     async fn remove_expired_approvals(
         executor: impl PgExecutor,
     ) -> anyhow::Result<Option<Vec<PendingApproval>>>;
+
+    async fn remove_decided_approvals(
+        executor: impl PgExecutor,
+    ) -> anyhow::Result<Vec<PendingApproval>>;
+
+    async fn update_approval_message(
+        executor: impl PgExecutor,
+        submission_id: i32,
+        message_id: u64,
+    ) -> anyhow::Result<PendingApproval>;
+
+    async fn get_all_pending_approvals(
+        executor: impl PgExecutor,
+    ) -> anyhow::Result<Vec<PendingApproval>>;
+
+    async fn import_pending_approval(
+        executor: impl PgExecutor,
+        pending_approval: PendingApproval,
+    ) -> anyhow::Result<bool>;
 }
 
 impl PendingApprovalHelpers for PendingApproval {
@@ -58,10 +78,12 @@ impl PendingApprovalHelpers for PendingApproval {
         Ok(added_approval)
     }
 
+    /// Gives [`None`] if the approval was already removed, like when it was
+    /// decided on by someone else first.
     async fn remove_pending_approval(
         executor: impl PgExecutor<'_>,
         remove_pending_approval: RemovePendingApproval,
-    ) -> anyhow::Result<Self> {
+    ) -> anyhow::Result<Option<Self>> {
         debug!("removing a pending approval");
         let removed_approval = match remove_pending_approval {
             RemovePendingApproval::MessageId(message_id) => {
@@ -70,7 +92,7 @@ impl PendingApprovalHelpers for PendingApproval {
                     "DELETE FROM pending_approvals WHERE message_id = $1 RETURNING *",
                     i64::try_from(message_id)?
                 )
-                .fetch_one(executor)
+                .fetch_optional(executor)
                 .await?
             }
             RemovePendingApproval::SubmissionId(submission_id) => {
@@ -79,16 +101,19 @@ impl PendingApprovalHelpers for PendingApproval {
                     "DELETE FROM pending_approvals WHERE submission_id = $1 RETURNING *",
                     submission_id
                 )
-                .fetch_one(executor)
+                .fetch_optional(executor)
                 .await?
             }
         };
 
-        debug!(
-            "removed a pending approval with: `submission_id`: {submission_id}, `message_id`: {message_id}",
-            submission_id = removed_approval.submission_id,
-            message_id = removed_approval.message_id
-        );
+        match &removed_approval {
+            Some(removed_approval) => debug!(
+                "removed a pending approval with: `submission_id`: {submission_id}, `message_id`: {message_id}",
+                submission_id = removed_approval.submission_id,
+                message_id = removed_approval.message_id
+            ),
+            None => debug!("no pending approval found to remove"),
+        }
         Ok(removed_approval)
     }
 
@@ -123,4 +148,89 @@ impl PendingApprovalHelpers for PendingApproval {
         );
         Ok(Some(expired_approvals))
     }
+
+    /// Removes approvals of submissions that were decided on without going
+    /// through their approval message.
+    async fn remove_decided_approvals(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        debug!("removing decided approvals");
+
+        let decided_approvals = sqlx::query_as!(
+            PendingApproval,
+            r#"
+            DELETE FROM pending_approvals WHERE submission_id IN
+                (SELECT submission_id FROM submissions WHERE decision IS NOT NULL)
+            RETURNING *
+            "#
+        )
+        .fetch_all(executor)
+        .await?;
+
+        if !decided_approvals.is_empty() {
+            debug!(
+                "removed decided approvals with: `submission_id`s: {submission_ids}",
+                submission_ids = decided_approvals
+                    .iter()
+                    .map(|approval| approval.submission_id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            );
+        }
+        Ok(decided_approvals)
+    }
+
+    /// Points an approval at the message that replaced its original one.
+    async fn update_approval_message(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+        message_id: u64,
+    ) -> anyhow::Result<Self> {
+        debug!("updating the message of a pending approval");
+
+        let updated_approval = sqlx::query_as!(
+            PendingApproval,
+            "UPDATE pending_approvals SET message_id = $2 WHERE submission_id = $1 RETURNING *",
+            submission_id,
+            i64::try_from(message_id)?
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "updated the message of a pending approval with: `submission_id`: {submission_id}, `message_id`: {message_id}",
+            submission_id = updated_approval.submission_id,
+            message_id = updated_approval.message_id
+        );
+        Ok(updated_approval)
+    }
+
+    async fn get_all_pending_approvals(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let pending_approvals = sqlx::query_as!(
+            PendingApproval,
+            "SELECT * FROM pending_approvals ORDER BY date"
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(pending_approvals)
+    }
+
+    /// Inserts a pending approval from an archive, returning whether it
+    /// wasn't there yet. `submission_id` has to be the one in this database.
+    async fn import_pending_approval(
+        executor: impl PgExecutor<'_>,
+        pending_approval: PendingApproval,
+    ) -> anyhow::Result<bool> {
+        let inserted = sqlx::query!(
+            "INSERT INTO pending_approvals (submission_id, message_id, date) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            pending_approval.submission_id,
+            pending_approval.message_id,
+            pending_approval.date
+        )
+        .execute(executor)
+        .await?
+        .rows_affected()
+            > 0;
+
+        Ok(inserted)
+    }
 }
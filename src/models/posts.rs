@@ -0,0 +1,362 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[sqlx(type_name = "post_state", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum PostState {
+    Pending,
+    Uploaded,
+    Published,
+    Failed,
+    Dead,
+    /// Left for a later tick because the target's rate limit ran out.
+    Deferred,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Post {
+    pub post_id: i32,
+    pub submission_id: i32,
+    /// Name of the [`crate::publisher::Publisher`] the post is sent to.
+    pub target: String,
+
+    pub idempotency_key: uuid::Uuid,
+    pub repost: bool,
+    pub state: PostState,
+
+    pub media_ids: Vec<String>,
+    pub status_id: Option<String>,
+    pub status_url: Option<String>,
+    pub error: Option<String>,
+
+    pub attempt_date: chrono::NaiveDateTime,
+    pub publish_date: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct AddPost {
+    pub submission_id: i32,
+    pub target: String,
+    pub repost: bool,
+}
+
+#[derive(Debug)]
+pub enum UpdatePost {
+    Uploaded {
+        media_ids: Vec<String>,
+    },
+    Published {
+        status_id: String,
+        status_url: Option<String>,
+    },
+    Failed {
+        error: String,
+    },
+    Dead {
+        error: String,
+    },
+    Deferred {
+        error: String,
+    },
+}
+
+pub trait PostHelpers {
+    async fn add_post(executor: impl PgExecutor, add_post: AddPost) -> anyhow::Result<Post>;
+
+    async fn update_post(
+        executor: impl PgExecutor,
+        post_id: i32,
+        update_post: UpdatePost,
+    ) -> anyhow::Result<Post>;
+
+    async fn get_unfinished_posts(executor: impl PgExecutor) -> anyhow::Result<Vec<Post>>;
+
+    async fn get_failed_post(
+        executor: impl PgExecutor,
+        submission_id: i32,
+        repost: bool,
+        target: &str,
+    ) -> anyhow::Result<Option<Post>>;
+
+    async fn count_failed_posts(
+        executor: impl PgExecutor,
+        submission_id: i32,
+        repost: bool,
+        target: &str,
+    ) -> anyhow::Result<i64>;
+
+    async fn get_finished_posts(
+        executor: impl PgExecutor,
+        submission_id: i32,
+    ) -> anyhow::Result<Vec<Post>>;
+
+    async fn get_published_posts(
+        executor: impl PgExecutor,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Post>>;
+
+    async fn get_last_publish_date(
+        executor: impl PgExecutor,
+    ) -> anyhow::Result<Option<chrono::NaiveDateTime>>;
+
+    async fn get_all_posts(executor: impl PgExecutor) -> anyhow::Result<Vec<Post>>;
+
+    async fn import_post(executor: impl PgExecutor, post: Post) -> anyhow::Result<bool>;
+}
+
+impl PostHelpers for Post {
+    /// Retries of a failed attempt get its idempotency key, since the target
+    /// may have published it even though it seemed to fail, like when the
+    /// response timed out. A deferred attempt passes on the key it was given.
+    /// Keys carry the time of the first attempt, which Bluesky record keys
+    /// are made from.
+    async fn add_post(executor: impl PgExecutor<'_>, add_post: AddPost) -> anyhow::Result<Self> {
+        debug!("adding a new post attempt");
+
+        let added_post = sqlx::query_as!(
+            Post,
+            r#"INSERT INTO posts (submission_id, target, idempotency_key, repost) VALUES ($1, $2, COALESCE((
+                SELECT idempotency_key FROM (
+                    SELECT idempotency_key, state FROM posts WHERE submission_id = $1 AND target = $2 AND repost = $4
+                    ORDER BY post_id DESC LIMIT 1
+                ) AS last_post WHERE state IN ('failed', 'deferred')
+            ), $3), $4)
+            RETURNING post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date"#,
+            add_post.submission_id,
+            add_post.target,
+            uuid::Uuid::now_v7(),
+            add_post.repost
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "added a new post attempt with: `post_id`: {post_id}, `submission_id`: {submission_id}, `target`: {target}",
+            post_id = added_post.post_id,
+            submission_id = added_post.submission_id,
+            target = added_post.target
+        );
+        Ok(added_post)
+    }
+
+    async fn update_post(
+        executor: impl PgExecutor<'_>,
+        post_id: i32,
+        update_post: UpdatePost,
+    ) -> anyhow::Result<Self> {
+        debug!("updating a post attempt");
+
+        let updated_post = match update_post {
+            UpdatePost::Uploaded { media_ids } => {
+                sqlx::query_as!(
+                    Post,
+                    r#"UPDATE posts SET state = 'uploaded', media_ids = $2 WHERE post_id = $1
+                    RETURNING post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date"#,
+                    post_id,
+                    &media_ids
+                )
+                .fetch_one(executor)
+                .await?
+            }
+            UpdatePost::Published {
+                status_id,
+                status_url,
+            } => {
+                sqlx::query_as!(
+                    Post,
+                    r#"UPDATE posts SET state = 'published', status_id = $2, status_url = $3, publish_date = NOW() WHERE post_id = $1
+                    RETURNING post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date"#,
+                    post_id,
+                    status_id,
+                    status_url
+                )
+                .fetch_one(executor)
+                .await?
+            }
+            UpdatePost::Failed { error } => {
+                sqlx::query_as!(
+                    Post,
+                    r#"UPDATE posts SET state = 'failed', error = $2 WHERE post_id = $1
+                    RETURNING post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date"#,
+                    post_id,
+                    error
+                )
+                .fetch_one(executor)
+                .await?
+            }
+            UpdatePost::Dead { error } => {
+                sqlx::query_as!(
+                    Post,
+                    r#"UPDATE posts SET state = 'dead', error = $2 WHERE post_id = $1
+                    RETURNING post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date"#,
+                    post_id,
+                    error
+                )
+                .fetch_one(executor)
+                .await?
+            }
+            UpdatePost::Deferred { error } => {
+                sqlx::query_as!(
+                    Post,
+                    r#"UPDATE posts SET state = 'deferred', error = $2 WHERE post_id = $1
+                    RETURNING post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date"#,
+                    post_id,
+                    error
+                )
+                .fetch_one(executor)
+                .await?
+            }
+        };
+
+        debug!(
+            "updated a post attempt with: `post_id`: {post_id}, `state`: {state:?}",
+            post_id = updated_post.post_id,
+            state = updated_post.state
+        );
+        Ok(updated_post)
+    }
+
+    async fn get_unfinished_posts(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        debug!("getting unfinished post attempts");
+
+        let unfinished_posts = sqlx::query_as!(
+            Post,
+            r#"SELECT post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date
+            FROM posts WHERE state IN ('pending', 'uploaded') ORDER BY attempt_date"#
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(unfinished_posts)
+    }
+
+    /// Gets the last attempt at posting a submission to the target, as long
+    /// as it failed or was deferred and so is going to be retried.
+    async fn get_failed_post(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+        repost: bool,
+        target: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        let failed_post = sqlx::query_as!(
+            Post,
+            r#"SELECT post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date
+            FROM (SELECT * FROM posts WHERE submission_id = $1 AND repost = $2 AND target = $3 ORDER BY post_id DESC LIMIT 1) AS last_post
+            WHERE state IN ('failed', 'deferred')"#,
+            submission_id,
+            repost,
+            target
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        Ok(failed_post)
+    }
+
+    async fn count_failed_posts(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+        repost: bool,
+        target: &str,
+    ) -> anyhow::Result<i64> {
+        let failed_posts = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM posts WHERE submission_id = $1 AND repost = $2 AND target = $3 AND state = 'failed'"#,
+            submission_id,
+            repost,
+            target
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(failed_posts)
+    }
+
+    /// Gets the attempts at first posting a submission that either made it
+    /// through or were given up on.
+    async fn get_finished_posts(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let finished_posts = sqlx::query_as!(
+            Post,
+            r#"SELECT post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date
+            FROM posts WHERE submission_id = $1 AND NOT repost AND state IN ('published', 'dead') ORDER BY attempt_date"#,
+            submission_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(finished_posts)
+    }
+
+    async fn get_published_posts(
+        executor: impl PgExecutor<'_>,
+        limit: i64,
+    ) -> anyhow::Result<Vec<Self>> {
+        // Every target's post of the last `limit` submissions that went out,
+        // newest first. Reposts are left out since they're the same content.
+        let published_posts = sqlx::query_as!(
+            Post,
+            r#"SELECT post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date
+            FROM posts WHERE NOT repost AND state = 'published' AND submission_id IN (
+                SELECT submission_id FROM posts WHERE NOT repost AND state = 'published'
+                GROUP BY submission_id ORDER BY MAX(publish_date) DESC LIMIT $1
+            ) ORDER BY publish_date DESC"#,
+            limit
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(published_posts)
+    }
+
+    async fn get_last_publish_date(
+        executor: impl PgExecutor<'_>,
+    ) -> anyhow::Result<Option<chrono::NaiveDateTime>> {
+        let last_publish_date =
+            sqlx::query_scalar!("SELECT MAX(publish_date) FROM posts WHERE state = 'published'")
+                .fetch_one(executor)
+                .await?;
+
+        Ok(last_publish_date)
+    }
+
+    async fn get_all_posts(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let posts = sqlx::query_as!(
+            Post,
+            r#"SELECT post_id, submission_id, target, idempotency_key, repost, state as "state: PostState", media_ids, status_id, status_url, error, attempt_date, publish_date
+            FROM posts ORDER BY post_id"#
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(posts)
+    }
+
+    /// Inserts a post from an archive, unless an attempt with its idempotency
+    /// key and date is already there. `submission_id` has to be the one in this database.
+    async fn import_post(executor: impl PgExecutor<'_>, post: Post) -> anyhow::Result<bool> {
+        let inserted = sqlx::query!(
+            r#"INSERT INTO posts (submission_id, target, idempotency_key, repost, state, media_ids, status_id, status_url, error, attempt_date, publish_date)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) ON CONFLICT (idempotency_key, attempt_date) DO NOTHING"#,
+            post.submission_id,
+            post.target,
+            post.idempotency_key,
+            post.repost,
+            post.state as PostState,
+            &post.media_ids,
+            post.status_id,
+            post.status_url,
+            post.error,
+            post.attempt_date,
+            post.publish_date
+        )
+        .execute(executor)
+        .await?
+        .rows_affected()
+            > 0;
+
+        Ok(inserted)
+    }
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Repost {
+    pub repost_id: i32,
+    pub submission_id: i32,
+    pub repost_date: chrono::NaiveDateTime,
+}
+
+pub trait RepostHelpers {
+    async fn add_repost(executor: impl PgExecutor, submission_id: i32) -> anyhow::Result<Repost>;
+
+    async fn get_all_reposts(executor: impl PgExecutor) -> anyhow::Result<Vec<Repost>>;
+
+    async fn import_repost(executor: impl PgExecutor, repost: Repost) -> anyhow::Result<bool>;
+}
+
+impl RepostHelpers for Repost {
+    async fn add_repost(executor: impl PgExecutor<'_>, submission_id: i32) -> anyhow::Result<Self> {
+        debug!("adding a new repost");
+
+        let added_repost = sqlx::query_as!(
+            Repost,
+            "INSERT INTO reposts (submission_id) VALUES ($1) RETURNING *",
+            submission_id
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "added a new repost with: `repost_id`: {repost_id}, `submission_id`: {submission_id}",
+            repost_id = added_repost.repost_id,
+            submission_id = added_repost.submission_id
+        );
+        Ok(added_repost)
+    }
+
+    async fn get_all_reposts(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let reposts = sqlx::query_as!(Repost, "SELECT * FROM reposts ORDER BY repost_id")
+            .fetch_all(executor)
+            .await?;
+
+        Ok(reposts)
+    }
+
+    /// Inserts a repost from an archive, unless the submission was already
+    /// reposted at the same date. `submission_id` has to be the one in this
+    /// database.
+    async fn import_repost(executor: impl PgExecutor<'_>, repost: Repost) -> anyhow::Result<bool> {
+        let inserted = sqlx::query!(
+            r#"INSERT INTO reposts (submission_id, repost_date) SELECT $1, $2
+            WHERE NOT EXISTS (SELECT FROM reposts WHERE submission_id = $1 AND repost_date = $2)"#,
+            repost.submission_id,
+            repost.repost_date
+        )
+        .execute(executor)
+        .await?
+        .rows_affected()
+            > 0;
+
+        Ok(inserted)
+    }
+}
@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReviewerVote {
+    pub submission_id: i32,
+    pub reviewer_id: i64,
+
+    pub approve: bool,
+    pub vote_date: chrono::NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct AddReviewerVote {
+    pub submission_id: i32,
+    pub reviewer_id: u64,
+    pub approve: bool,
+}
+
+pub trait ReviewerVoteHelpers {
+    async fn add_reviewer_vote(
+        executor: impl PgExecutor,
+        add_reviewer_vote: AddReviewerVote,
+    ) -> anyhow::Result<ReviewerVote>;
+
+    async fn get_reviewer_votes(
+        executor: impl PgExecutor,
+        submission_id: i32,
+    ) -> anyhow::Result<Vec<ReviewerVote>>;
+
+    async fn get_all_reviewer_votes(executor: impl PgExecutor)
+        -> anyhow::Result<Vec<ReviewerVote>>;
+
+    async fn import_reviewer_vote(
+        executor: impl PgExecutor,
+        reviewer_vote: ReviewerVote,
+    ) -> anyhow::Result<bool>;
+}
+
+impl ReviewerVoteHelpers for ReviewerVote {
+    /// Replaces the reviewer's earlier vote on the submission, if they had
+    /// one.
+    async fn add_reviewer_vote(
+        executor: impl PgExecutor<'_>,
+        add_reviewer_vote: AddReviewerVote,
+    ) -> anyhow::Result<Self> {
+        debug!("adding a new reviewer vote");
+
+        let added_vote = sqlx::query_as!(
+            ReviewerVote,
+            r#"INSERT INTO reviewer_votes (submission_id, reviewer_id, approve) VALUES ($1, $2, $3)
+            ON CONFLICT (submission_id, reviewer_id) DO UPDATE SET approve = EXCLUDED.approve, vote_date = NOW()
+            RETURNING *"#,
+            add_reviewer_vote.submission_id,
+            i64::try_from(add_reviewer_vote.reviewer_id)?,
+            add_reviewer_vote.approve
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "added a new reviewer vote with: `submission_id`: {submission_id}, `reviewer_id`: {reviewer_id}, `approve`: {approve}",
+            submission_id = added_vote.submission_id,
+            reviewer_id = added_vote.reviewer_id,
+            approve = added_vote.approve
+        );
+        Ok(added_vote)
+    }
+
+    /// Votes on a submission in the order they were cast.
+    async fn get_reviewer_votes(
+        executor: impl PgExecutor<'_>,
+        submission_id: i32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let reviewer_votes = sqlx::query_as!(
+            ReviewerVote,
+            "SELECT * FROM reviewer_votes WHERE submission_id = $1 ORDER BY vote_date, reviewer_id",
+            submission_id
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(reviewer_votes)
+    }
+
+    async fn get_all_reviewer_votes(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let reviewer_votes = sqlx::query_as!(
+            ReviewerVote,
+            "SELECT * FROM reviewer_votes ORDER BY submission_id, vote_date, reviewer_id"
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(reviewer_votes)
+    }
+
+    /// Inserts a reviewer vote from an archive, returning whether the
+    /// reviewer hadn't voted on the submission yet. `submission_id` has to be
+    /// the one in this database.
+    async fn import_reviewer_vote(
+        executor: impl PgExecutor<'_>,
+        reviewer_vote: ReviewerVote,
+    ) -> anyhow::Result<bool> {
+        let inserted = sqlx::query!(
+            "INSERT INTO reviewer_votes (submission_id, reviewer_id, approve, vote_date) VALUES ($1, $2, $3, $4) ON CONFLICT DO NOTHING",
+            reviewer_vote.submission_id,
+            reviewer_vote.reviewer_id,
+            reviewer_vote.approve,
+            reviewer_vote.vote_date
+        )
+        .execute(executor)
+        .await?
+        .rows_affected()
+            > 0;
+
+        Ok(inserted)
+    }
+}
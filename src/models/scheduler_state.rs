@@ -0,0 +1,107 @@
+use sqlx::PgExecutor;
+
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "scheduler_status", rename_all = "lowercase")]
+pub enum SchedulerStatus {
+    Running,
+    Paused,
+}
+
+#[derive(Debug)]
+pub struct SchedulerState {
+    pub status: SchedulerStatus,
+    pub reason: Option<String>,
+    pub changed_by: Option<i64>,
+    pub change_date: chrono::NaiveDateTime,
+
+    pub empty_queue_notice_date: Option<chrono::NaiveDateTime>,
+    pub low_queue_warning_date: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct UpdateSchedulerState {
+    pub status: SchedulerStatus,
+    pub reason: Option<String>,
+    pub changed_by: Option<u64>,
+}
+
+pub enum SchedulerNotice {
+    EmptyQueue,
+    LowQueue,
+}
+
+pub trait SchedulerStateHelpers {
+    async fn get_scheduler_state(executor: impl PgExecutor) -> anyhow::Result<SchedulerState>;
+
+    async fn update_scheduler_state(
+        executor: impl PgExecutor,
+        update_scheduler_state: UpdateSchedulerState,
+    ) -> anyhow::Result<SchedulerState>;
+
+    async fn mark_scheduler_notice(
+        executor: impl PgExecutor,
+        scheduler_notice: SchedulerNotice,
+    ) -> anyhow::Result<()>;
+}
+
+impl SchedulerStateHelpers for SchedulerState {
+    async fn get_scheduler_state(executor: impl PgExecutor<'_>) -> anyhow::Result<Self> {
+        debug!("getting the scheduler state");
+
+        let scheduler_state = sqlx::query_as!(
+            SchedulerState,
+            r#"SELECT status as "status: SchedulerStatus", reason, changed_by, change_date, empty_queue_notice_date, low_queue_warning_date FROM scheduler_state"#
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(scheduler_state)
+    }
+
+    async fn update_scheduler_state(
+        executor: impl PgExecutor<'_>,
+        update_scheduler_state: UpdateSchedulerState,
+    ) -> anyhow::Result<Self> {
+        debug!("updating the scheduler state");
+
+        let updated_state = sqlx::query_as!(
+            SchedulerState,
+            r#"UPDATE scheduler_state SET status = $1, reason = $2, changed_by = $3, change_date = NOW()
+            RETURNING status as "status: SchedulerStatus", reason, changed_by, change_date, empty_queue_notice_date, low_queue_warning_date"#,
+            update_scheduler_state.status as SchedulerStatus,
+            update_scheduler_state.reason,
+            update_scheduler_state
+                .changed_by
+                .map(i64::try_from)
+                .transpose()?
+        )
+        .fetch_one(executor)
+        .await?;
+
+        debug!(
+            "updated the scheduler state with: `status`: {status:?}",
+            status = updated_state.status
+        );
+        Ok(updated_state)
+    }
+
+    async fn mark_scheduler_notice(
+        executor: impl PgExecutor<'_>,
+        scheduler_notice: SchedulerNotice,
+    ) -> anyhow::Result<()> {
+        match scheduler_notice {
+            SchedulerNotice::EmptyQueue => {
+                sqlx::query!("UPDATE scheduler_state SET empty_queue_notice_date = NOW()")
+                    .execute(executor)
+                    .await?;
+            }
+            SchedulerNotice::LowQueue => {
+                sqlx::query!("UPDATE scheduler_state SET low_queue_warning_date = NOW()")
+                    .execute(executor)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,13 +1,17 @@
+use serde::{Deserialize, Serialize};
 use sqlx::PgExecutor;
 
-#[derive(sqlx::Type)]
+use super::ImportedRow;
+
+#[derive(sqlx::Type, Serialize, Deserialize)]
 #[sqlx(type_name = "submission_decision", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum SubmissionDecision {
     Approved,
     Rejected,
 }
 
-#[derive(sqlx::FromRow)]
+#[derive(sqlx::FromRow, Serialize, Deserialize)]
 pub struct Submission {
     pub submission_id: i32,
     pub user_id: i64,
@@ -16,13 +20,14 @@ pub struct Submission {
     pub art_link: String,
     pub additional_information: Option<String>,
 
-    pub sample_image_url: Option<String>,
+    pub content_warning: Option<String>,
 
     pub decision: Option<SubmissionDecision>,
 
     #[sqlx(default)]
     pub submission_date: chrono::NaiveDateTime,
     pub submission_decision_date: Option<chrono::NaiveDateTime>,
+    pub submission_post_date: Option<chrono::NaiveDateTime>,
 }
 
 pub struct AddSubmission {
@@ -30,7 +35,7 @@ pub struct AddSubmission {
     pub artist: String,
     pub art_link: String,
     pub additional_information: Option<String>,
-    pub sample_image_url: Option<String>,
+    pub content_warning: Option<String>,
 }
 
 pub enum SubmissionIds {
@@ -52,12 +57,48 @@ pub trait SubmissionHelpers {
     async fn approve_submission(
         executor: impl PgExecutor,
         approve_submission: SubmissionIds,
-    ) -> anyhow::Result<Submission>;
+    ) -> anyhow::Result<Option<Submission>>;
 
     async fn reject_submission(
         executor: impl PgExecutor,
         submission_id: SubmissionIds,
+    ) -> anyhow::Result<Option<Submission>>;
+
+    async fn get_submission(
+        executor: impl PgExecutor,
+        submission_id: SubmissionIds,
+    ) -> anyhow::Result<Submission>;
+
+    async fn get_next_queued_submission(
+        executor: impl PgExecutor,
+        require_alt_text: bool,
+        targets: &[String],
+    ) -> anyhow::Result<Option<Submission>>;
+
+    async fn mark_submission_posted(
+        executor: impl PgExecutor,
+        submission_id: SubmissionIds,
     ) -> anyhow::Result<Submission>;
+
+    async fn count_queued_submissions(
+        executor: impl PgExecutor,
+        require_alt_text: bool,
+        targets: &[String],
+    ) -> anyhow::Result<i64>;
+
+    async fn get_archived_submission(
+        executor: impl PgExecutor,
+        repost_gap_days: i32,
+    ) -> anyhow::Result<Option<Submission>>;
+
+    async fn get_all_submissions(executor: impl PgExecutor) -> anyhow::Result<Vec<Submission>>;
+
+    async fn get_art_links(executor: impl PgExecutor) -> anyhow::Result<Vec<String>>;
+
+    async fn import_submission(
+        executor: impl PgExecutor,
+        submission: Submission,
+    ) -> anyhow::Result<ImportedRow>;
 }
 
 impl SubmissionHelpers for Submission {
@@ -69,14 +110,14 @@ impl SubmissionHelpers for Submission {
 
         let created_submission = sqlx::query_as!(
             Submission,
-            r#"INSERT INTO submissions(user_id, artist, art_link, additional_information, sample_image_url)
+            r#"INSERT INTO submissions(user_id, artist, art_link, additional_information, content_warning)
             VALUES ($1, $2, $3, $4, $5)
-            RETURNING submission_id, user_id, artist, art_link, additional_information, sample_image_url, decision as "decision: SubmissionDecision", submission_date, submission_decision_date"#,
+            RETURNING submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date"#,
             i64::try_from(submission.user_id)?,
             submission.artist,
             submission.art_link,
             submission.additional_information,
-            submission.sample_image_url
+            submission.content_warning
         )
             .fetch_one(executor)
             .await?;
@@ -88,28 +129,30 @@ impl SubmissionHelpers for Submission {
         Ok(created_submission)
     }
 
+    /// Gives [`None`] if the submission was already decided on, so only the
+    /// first decision on it counts.
     async fn approve_submission(
         executor: impl PgExecutor<'_>,
         approve_submission: SubmissionIds,
-    ) -> anyhow::Result<Self> {
+    ) -> anyhow::Result<Option<Self>> {
         debug!("approving a submission");
 
         let approved_submission = match approve_submission {
             SubmissionIds::SubmissionId(submission_id) => {
                 sqlx::query_as!(
                     Submission,
-                    r#"UPDATE submissions SET decision = 'approved', submission_decision_date = NOW() WHERE submission_id = $1
-                    RETURNING submission_id, user_id, artist, art_link, additional_information, sample_image_url, decision as "decision: SubmissionDecision", submission_date, submission_decision_date"#,
+                    r#"UPDATE submissions SET decision = 'approved', submission_decision_date = NOW() WHERE submission_id = $1 AND decision IS NULL
+                    RETURNING submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date"#,
                     submission_id
                 )
-                .fetch_one(executor)
+                .fetch_optional(executor)
                 .await?
             }
             // SubmissionIds::UserId(user_id) => {
             //     sqlx::query_as!(
             //         Submission,
             //         r#"UPDATE submissions SET decision = 'approved', submission_decision_date = NOW() WHERE user_id = $1
-            //         RETURNING submission_id, user_id, artist, art_link, additional_information, sample_image_url, decision as "decision: SubmissionDecision", submission_date, submission_decision_date"#,
+            //         RETURNING submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date"#,
             //         user_id as i64
             //     )
             //     .fetch_one(executor)
@@ -117,35 +160,40 @@ impl SubmissionHelpers for Submission {
             // }
         };
 
-        debug!(
-            "approved a submission with: `submission_id`: {submission_id}",
-            submission_id = approved_submission.submission_id
-        );
+        match &approved_submission {
+            Some(approved_submission) => debug!(
+                "approved a submission with: `submission_id`: {submission_id}",
+                submission_id = approved_submission.submission_id
+            ),
+            None => debug!("submission was already decided on, not approving it"),
+        }
         Ok(approved_submission)
     }
 
+    /// Gives [`None`] if the submission was already decided on, so only the
+    /// first decision on it counts.
     async fn reject_submission(
         executor: impl PgExecutor<'_>,
         submission_id: SubmissionIds,
-    ) -> anyhow::Result<Self> {
+    ) -> anyhow::Result<Option<Self>> {
         debug!("rejecting a submission");
 
         let rejected_submission = match submission_id {
             SubmissionIds::SubmissionId(submission_id) => {
                 sqlx::query_as!(
                     Submission,
-                    r#"UPDATE submissions SET decision = 'rejected', submission_decision_date = NOW() WHERE submission_id = $1
-                    RETURNING submission_id, user_id, artist, art_link, additional_information, sample_image_url, decision as "decision: SubmissionDecision", submission_date, submission_decision_date"#,
+                    r#"UPDATE submissions SET decision = 'rejected', submission_decision_date = NOW() WHERE submission_id = $1 AND decision IS NULL
+                    RETURNING submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date"#,
                     submission_id
                 )
-                .fetch_one(executor)
+                .fetch_optional(executor)
                 .await?
             }
             // SubmissionIds::UserId(user_id) => {
             //     sqlx::query_as!(
             //         Submission,
             //         r#"UPDATE submissions SET decision = 'rejected', submission_decision_date = NOW() WHERE user_id = $1
-            //         RETURNING submission_id, user_id, artist, art_link, additional_information, sample_image_url, decision as "decision: SubmissionDecision", submission_date, submission_decision_date"#,
+            //         RETURNING submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date"#,
             //         user_id as i64
             //     )
             //     .fetch_one(executor)
@@ -153,11 +201,210 @@ impl SubmissionHelpers for Submission {
             // }
         };
 
+        match &rejected_submission {
+            Some(rejected_submission) => debug!(
+                "rejected a submission with: `submission_id`: {submission_id}, `user_id`: {user_id}",
+                submission_id = rejected_submission.submission_id,
+                user_id = rejected_submission.user_id
+            ),
+            None => debug!("submission was already decided on, not rejecting it"),
+        }
+        Ok(rejected_submission)
+    }
+
+    async fn get_submission(
+        executor: impl PgExecutor<'_>,
+        submission_id: SubmissionIds,
+    ) -> anyhow::Result<Self> {
+        let submission = match submission_id {
+            SubmissionIds::SubmissionId(submission_id) => {
+                sqlx::query_as!(
+                    Submission,
+                    r#"SELECT submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date
+                    FROM submissions WHERE submission_id = $1"#,
+                    submission_id
+                )
+                .fetch_one(executor)
+                .await?
+            }
+        };
+
+        Ok(submission)
+    }
+
+    async fn get_next_queued_submission(
+        executor: impl PgExecutor<'_>,
+        require_alt_text: bool,
+        targets: &[String],
+    ) -> anyhow::Result<Option<Self>> {
+        debug!("getting the next queued submission");
+
+        let queued_submission = sqlx::query_as!(
+            Submission,
+            r#"SELECT submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date
+            FROM submissions WHERE decision = 'approved' AND submission_post_date IS NULL
+            AND (NOT $1 OR NOT EXISTS (
+                SELECT FROM images WHERE images.submission_id = submissions.submission_id
+                AND alt_text IS NULL
+            ))
+            AND NOT EXISTS (
+                SELECT FROM posts WHERE posts.submission_id = submissions.submission_id
+                AND NOT repost AND state IN ('pending', 'uploaded')
+            )
+            AND NOT $2::TEXT[] <@ ARRAY(
+                SELECT target FROM posts WHERE posts.submission_id = submissions.submission_id
+                AND NOT repost AND state = 'dead'
+            )
+            ORDER BY submission_decision_date LIMIT 1"#,
+            require_alt_text,
+            targets
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        if queued_submission.is_none() {
+            debug!("no queued submissions found");
+        }
+
+        Ok(queued_submission)
+    }
+
+    async fn mark_submission_posted(
+        executor: impl PgExecutor<'_>,
+        submission_id: SubmissionIds,
+    ) -> anyhow::Result<Self> {
+        debug!("marking a submission as posted");
+
+        let posted_submission = match submission_id {
+            SubmissionIds::SubmissionId(submission_id) => {
+                sqlx::query_as!(
+                    Submission,
+                    r#"UPDATE submissions SET submission_post_date = NOW() WHERE submission_id = $1
+                    RETURNING submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date"#,
+                    submission_id
+                )
+                .fetch_one(executor)
+                .await?
+            }
+        };
+
         debug!(
-            "rejected a submission with: `submission_id`: {submission_id}, `user_id`: {user_id}",
-            submission_id = rejected_submission.submission_id,
-            user_id = rejected_submission.user_id
+            "marked a submission as posted with: `submission_id`: {submission_id}",
+            submission_id = posted_submission.submission_id
         );
-        Ok(rejected_submission)
+        Ok(posted_submission)
+    }
+
+    async fn count_queued_submissions(
+        executor: impl PgExecutor<'_>,
+        require_alt_text: bool,
+        targets: &[String],
+    ) -> anyhow::Result<i64> {
+        let queued_submissions = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM submissions WHERE decision = 'approved' AND submission_post_date IS NULL
+            AND (NOT $1 OR NOT EXISTS (
+                SELECT FROM images WHERE images.submission_id = submissions.submission_id
+                AND alt_text IS NULL
+            ))
+            AND NOT EXISTS (
+                SELECT FROM posts WHERE posts.submission_id = submissions.submission_id
+                AND NOT repost AND state IN ('pending', 'uploaded')
+            )
+            AND NOT $2::TEXT[] <@ ARRAY(
+                SELECT target FROM posts WHERE posts.submission_id = submissions.submission_id
+                AND NOT repost AND state = 'dead'
+            )"#,
+            require_alt_text,
+            targets
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(queued_submissions)
+    }
+
+    async fn get_archived_submission(
+        executor: impl PgExecutor<'_>,
+        repost_gap_days: i32,
+    ) -> anyhow::Result<Option<Self>> {
+        debug!("getting the top voted archived submission");
+
+        let archived_submission = sqlx::query_as!(
+            Submission,
+            r#"SELECT submissions.submission_id, submissions.user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date
+            FROM submissions
+            LEFT JOIN votes ON votes.submission_id = submissions.submission_id
+            WHERE submission_post_date < NOW() - make_interval(days => $1)
+            AND NOT EXISTS (
+                SELECT FROM reposts WHERE reposts.submission_id = submissions.submission_id
+                AND repost_date > NOW() - make_interval(days => $1)
+            )
+            GROUP BY submissions.submission_id
+            ORDER BY COALESCE(SUM(CASE WHEN vote THEN 1 ELSE -1 END), 0) DESC, RANDOM()
+            LIMIT 1"#,
+            repost_gap_days
+        )
+        .fetch_optional(executor)
+        .await?;
+
+        if archived_submission.is_none() {
+            debug!("no archived submissions found to repost");
+        }
+
+        Ok(archived_submission)
+    }
+
+    async fn get_all_submissions(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let submissions = sqlx::query_as!(
+            Submission,
+            r#"SELECT submission_id, user_id, artist, art_link, additional_information, content_warning, decision as "decision: SubmissionDecision", submission_date, submission_decision_date, submission_post_date
+            FROM submissions ORDER BY submission_id"#
+        )
+        .fetch_all(executor)
+        .await?;
+
+        Ok(submissions)
+    }
+
+    async fn get_art_links(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<String>> {
+        let art_links = sqlx::query_scalar!("SELECT art_link FROM submissions")
+            .fetch_all(executor)
+            .await?;
+
+        Ok(art_links)
+    }
+
+    /// Inserts a submission from an archive, unless one from the same user
+    /// with the same link and date is already there. `submission_id` is left
+    /// for the database to pick.
+    async fn import_submission(
+        executor: impl PgExecutor<'_>,
+        submission: Submission,
+    ) -> anyhow::Result<ImportedRow> {
+        let imported_submission = sqlx::query_as!(
+            ImportedRow,
+            r#"WITH existing_submission AS (
+                SELECT submission_id FROM submissions WHERE user_id = $1 AND art_link = $2 AND submission_date = $3
+            ), inserted_submission AS (
+                INSERT INTO submissions (user_id, art_link, submission_date, artist, additional_information, content_warning, decision, submission_decision_date, submission_post_date)
+                SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9 WHERE NOT EXISTS (SELECT FROM existing_submission)
+                RETURNING submission_id
+            )
+            SELECT submission_id as "id!", false as "inserted!" FROM existing_submission
+            UNION ALL SELECT submission_id, true FROM inserted_submission"#,
+            submission.user_id,
+            submission.art_link,
+            submission.submission_date,
+            submission.artist,
+            submission.additional_information,
+            submission.content_warning,
+            submission.decision as Option<SubmissionDecision>,
+            submission.submission_decision_date,
+            submission.submission_post_date
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(imported_submission)
     }
 }
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+
+use super::ImportedRow;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Vote {
+    pub vote_id: i32,
+    pub submission_id: i32,
+    pub user_id: i64,
+
+    pub vote: bool,
+    pub vote_date: chrono::NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct VoteCounts {
+    pub upvotes: i64,
+    pub downvotes: i64,
+}
+
+pub trait VoteHelpers {
+    async fn count_votes(executor: impl PgExecutor) -> anyhow::Result<VoteCounts>;
+
+    async fn get_all_votes(executor: impl PgExecutor) -> anyhow::Result<Vec<Vote>>;
+
+    async fn import_vote(executor: impl PgExecutor, vote: Vote) -> anyhow::Result<ImportedRow>;
+}
+
+impl VoteHelpers for Vote {
+    async fn count_votes(executor: impl PgExecutor<'_>) -> anyhow::Result<VoteCounts> {
+        let vote_counts = sqlx::query_as!(
+            VoteCounts,
+            r#"SELECT COUNT(*) FILTER (WHERE vote) as "upvotes!", COUNT(*) FILTER (WHERE NOT vote) as "downvotes!" FROM votes"#
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(vote_counts)
+    }
+
+    async fn get_all_votes(executor: impl PgExecutor<'_>) -> anyhow::Result<Vec<Self>> {
+        let votes = sqlx::query_as!(Vote, "SELECT * FROM votes ORDER BY vote_id")
+            .fetch_all(executor)
+            .await?;
+
+        Ok(votes)
+    }
+
+    /// Inserts a vote from an archive, unless the user's vote from the same
+    /// date is already there. `submission_id` has to be the one in this
+    /// database.
+    async fn import_vote(executor: impl PgExecutor<'_>, vote: Vote) -> anyhow::Result<ImportedRow> {
+        let imported_vote = sqlx::query_as!(
+            ImportedRow,
+            r#"WITH existing_vote AS (
+                SELECT vote_id FROM votes WHERE submission_id = $1 AND user_id = $2 AND vote_date = $3
+            ), inserted_vote AS (
+                INSERT INTO votes (submission_id, user_id, vote_date, vote)
+                SELECT $1, $2, $3, $4 WHERE NOT EXISTS (SELECT FROM existing_vote)
+                RETURNING vote_id
+            )
+            SELECT vote_id as "id!", false as "inserted!" FROM existing_vote
+            UNION ALL SELECT vote_id, true FROM inserted_vote"#,
+            vote.submission_id,
+            vote.user_id,
+            vote.vote_date,
+            vote.vote
+        )
+        .fetch_one(executor)
+        .await?;
+
+        Ok(imported_vote)
+    }
+}
@@ -0,0 +1,52 @@
+use crate::media::Media;
+
+pub use self::retry::{PublishError, RetryPolicy};
+
+mod retry;
+
+/// A post ready to be sent to a target, with its media already uploaded.
+#[derive(Debug)]
+pub struct Publication {
+    pub text: String,
+    /// Link in `text` that should be made clickable on targets that need it
+    /// marked explicitly.
+    pub source_link: Option<String>,
+    pub media_ids: Vec<String>,
+    pub content_warning: Option<String>,
+    pub sensitive: bool,
+    /// Whether it's a submission coming back from the archive.
+    pub repost: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Published {
+    pub id: String,
+    pub url: Option<String>,
+}
+
+/// A place posts are published to. Every post attempt is tracked per target,
+/// using the name returned by [`Publisher::target`].
+#[async_trait::async_trait]
+pub trait Publisher: Send + Sync {
+    fn target(&self) -> &'static str;
+
+    /// Uploads media and returns an ID for it that can be put in
+    /// [`Publication::media_ids`].
+    async fn upload_media(
+        &self,
+        media: &Media,
+        description: Option<&str>,
+    ) -> anyhow::Result<String>;
+
+    /// Publishes a post. Sending the same `idempotency_key` again should not
+    /// publish it twice where the target supports it.
+    async fn publish(
+        &self,
+        publication: &Publication,
+        idempotency_key: uuid::Uuid,
+    ) -> anyhow::Result<Published>;
+
+    /// Looks for a recently published post with any of the given media, to
+    /// find out whether an interrupted attempt made it through.
+    async fn find_published(&self, media_ids: &[String]) -> anyhow::Result<Option<Published>>;
+}
@@ -0,0 +1,503 @@
+use std::{
+    fmt,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use reqwest::{header::HeaderMap, Response, StatusCode};
+use tokio::sync::Mutex;
+
+use crate::config::RetryConfig;
+
+#[derive(Debug)]
+pub enum PublishError {
+    /// The request may succeed if it's sent again later, like on timeouts,
+    /// 5xx responses or rate limits.
+    Transient {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+    /// The rate limit resets later than we're willing to wait, so the
+    /// request is left for the next tick. This isn't a failure of the post.
+    RateLimited {
+        message: String,
+        reset_after: Duration,
+    },
+    /// The request was rejected and sending it again won't change that.
+    Permanent {
+        message: String,
+        status: Option<StatusCode>,
+    },
+}
+
+impl PublishError {
+    pub const fn is_permanent(&self) -> bool {
+        matches!(self, Self::Permanent { .. })
+    }
+
+    pub const fn is_rate_limited(&self) -> bool {
+        matches!(self, Self::RateLimited { .. })
+    }
+
+    /// The status of the response that was rejected, if there was one.
+    pub const fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Permanent { status, .. } => *status,
+            Self::Transient { .. } | Self::RateLimited { .. } => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Transient { message, .. }
+            | Self::RateLimited { message, .. }
+            | Self::Permanent { message, .. } => message,
+        }
+    }
+
+    fn from_request_error(error: &reqwest::Error) -> Self {
+        if error.is_timeout() || error.is_connect() || error.is_request() || error.is_body() {
+            Self::Transient {
+                message: error.to_string(),
+                retry_after: None,
+            }
+        } else {
+            Self::Permanent {
+                message: error.to_string(),
+                status: error.status(),
+            }
+        }
+    }
+
+    async fn from_response(response: Response) -> Self {
+        let status = response.status();
+        let retry_after = if status == StatusCode::TOO_MANY_REQUESTS {
+            rate_limit_reset(response.headers())
+        } else {
+            None
+        };
+        let message = format!(
+            "{status}: {body}",
+            body = response.text().await.unwrap_or_default()
+        );
+
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || status == StatusCode::REQUEST_TIMEOUT
+            || status.is_server_error()
+        {
+            Self::Transient {
+                message,
+                retry_after,
+            }
+        } else {
+            Self::Permanent {
+                message,
+                status: Some(status),
+            }
+        }
+    }
+}
+
+impl fmt::Display for PublishError {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Transient { message, .. } => write!(formatter, "transient failure: {message}"),
+            Self::RateLimited {
+                message,
+                reset_after,
+            } => write!(
+                formatter,
+                "rate limited for another {reset_after:?}: {message}"
+            ),
+            Self::Permanent { message, .. } => write!(formatter, "permanent failure: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for PublishError {}
+
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    /// When the rate limit resets, if a response said there are no requests
+    /// left until then.
+    rate_limit_reset: Mutex<Option<Instant>>,
+}
+
+impl RetryPolicy {
+    pub fn new(retry_config: &RetryConfig) -> Self {
+        Self {
+            max_attempts: retry_config.max_attempts.max(1),
+            base_delay: Duration::from_millis(retry_config.base_delay_ms),
+            max_delay: Duration::from_secs(retry_config.max_delay_secs),
+            rate_limit_reset: Mutex::new(None),
+        }
+    }
+
+    /// Sends the request built by `send` until it succeeds, fails permanently
+    /// or runs out of attempts. `send` is called again for every attempt since
+    /// request bodies like multipart forms can't be reused.
+    pub async fn send<F, Fut>(&self, send: F) -> Result<Response, PublishError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = reqwest::Result<Response>>,
+    {
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+
+            if let Some(reset_after) = self.rate_limit_reset_after().await {
+                if reset_after > self.max_delay {
+                    return Err(PublishError::RateLimited {
+                        message: "no requests are left until the rate limit resets".to_string(),
+                        reset_after,
+                    });
+                }
+
+                debug!("waiting {reset_after:?} for the rate limit to reset");
+                tokio::time::sleep(reset_after).await;
+            }
+
+            let error = match send().await {
+                Ok(response) => {
+                    self.update_rate_limit(response.headers()).await;
+                    if response.status().is_success() {
+                        return Ok(response);
+                    }
+
+                    PublishError::from_response(response).await
+                }
+                Err(error) => PublishError::from_request_error(&error),
+            };
+
+            let PublishError::Transient {
+                message,
+                retry_after,
+            } = error
+            else {
+                return Err(error);
+            };
+
+            // Retrying before the rate limit resets would only be rejected
+            // again, so it's left for later if it's further away than we're
+            // willing to wait.
+            if let Some(reset_after) =
+                retry_after.filter(|reset_after| *reset_after > self.max_delay)
+            {
+                return Err(PublishError::RateLimited {
+                    message,
+                    reset_after,
+                });
+            }
+
+            let error = PublishError::Transient {
+                message,
+                retry_after,
+            };
+            if attempt >= self.max_attempts {
+                return Err(error);
+            }
+
+            let delay = retry_after.unwrap_or_else(|| self.backoff(attempt));
+
+            warn!(
+                "request failed on attempt {attempt}/{max_attempts}, retrying in {delay:?}: {error}",
+                max_attempts = self.max_attempts
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    async fn rate_limit_reset_after(&self) -> Option<Duration> {
+        let mut rate_limit_reset = self.rate_limit_reset.lock().await;
+        let reset_after = rate_limit_reset
+            .and_then(|reset| reset.checked_duration_since(Instant::now()))
+            .filter(|reset_after| !reset_after.is_zero());
+        if reset_after.is_none() {
+            *rate_limit_reset = None;
+        }
+        drop(rate_limit_reset);
+
+        reset_after
+    }
+
+    /// Remembers when the rate limit resets once a response, successful or
+    /// not, says there are no requests left.
+    async fn update_rate_limit(&self, headers: &HeaderMap) {
+        let exhausted = headers
+            .get("X-RateLimit-Remaining")
+            .and_then(|remaining| remaining.to_str().ok())
+            .and_then(|remaining| remaining.parse::<u64>().ok())
+            == Some(0);
+        if !exhausted {
+            return;
+        }
+
+        if let Some(reset_after) = rate_limit_reset(headers) {
+            *self.rate_limit_reset.lock().await = Some(Instant::now() + reset_after);
+        }
+    }
+
+    /// Exponential backoff with full jitter.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2_u32.saturating_pow(attempt - 1))
+            .min(self.max_delay);
+
+        rand::thread_rng().gen_range(Duration::ZERO..=ceiling)
+    }
+}
+
+fn rate_limit_reset(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get("X-RateLimit-Reset")
+        .and_then(|reset| reset.to_str().ok())
+        .and_then(|reset| chrono::DateTime::parse_from_rfc3339(reset).ok())
+        .map(|reset| {
+            (reset.with_timezone(&chrono::Utc) - chrono::Utc::now())
+                .to_std()
+                .unwrap_or_default()
+        })
+        .or_else(|| {
+            headers
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|retry_after| retry_after.to_str().ok())
+                .and_then(|retry_after| retry_after.parse().ok())
+                .map(Duration::from_secs)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use axum::{
+        extract::State,
+        http::{HeaderMap, StatusCode},
+        routing::post,
+        Router,
+    };
+    use reqwest::Client;
+
+    use super::*;
+
+    /// What the mock server does for one request.
+    enum Scripted {
+        Respond(u16, Vec<(&'static str, String)>),
+        Hang,
+    }
+
+    type Script = Arc<std::sync::Mutex<VecDeque<Scripted>>>;
+
+    struct MockServer {
+        address: SocketAddr,
+        requests: Arc<AtomicUsize>,
+    }
+
+    /// Serves the scripted responses in order, then `200 OK` for everything
+    /// after them.
+    async fn mock_server(script: Vec<Scripted>) -> MockServer {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let script = Arc::new(std::sync::Mutex::new(VecDeque::from(script)));
+
+        let app = Router::new()
+            .route(
+                "/",
+                post(
+                    |State((script, requests)): State<(Script, Arc<AtomicUsize>)>| async move {
+                        requests.fetch_add(1, Ordering::SeqCst);
+                        let scripted = script.lock().unwrap().pop_front();
+                        match scripted {
+                            Some(Scripted::Respond(status, headers)) => {
+                                let mut header_map = HeaderMap::new();
+                                for (name, value) in headers {
+                                    header_map.insert(name, value.parse().unwrap());
+                                }
+                                (StatusCode::from_u16(status).unwrap(), header_map)
+                            }
+                            Some(Scripted::Hang) => {
+                                tokio::time::sleep(Duration::from_secs(60)).await;
+                                (StatusCode::OK, HeaderMap::new())
+                            }
+                            None => (StatusCode::OK, HeaderMap::new()),
+                        }
+                    },
+                ),
+            )
+            .with_state((script, Arc::clone(&requests)));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        MockServer { address, requests }
+    }
+
+    impl MockServer {
+        fn requests(&self) -> usize {
+            self.requests.load(Ordering::SeqCst)
+        }
+    }
+
+    fn retry_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(&RetryConfig {
+            max_attempts,
+            base_delay_ms: 1,
+            max_delay_secs: 5,
+        })
+    }
+
+    async fn send(
+        retry_policy: &RetryPolicy,
+        server: &MockServer,
+    ) -> Result<Response, PublishError> {
+        let client = Client::builder()
+            .timeout(Duration::from_millis(200))
+            .build()
+            .unwrap();
+        let url = format!("http://{address}/", address = server.address);
+
+        retry_policy.send(|| client.post(&url).send()).await
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_until_success() {
+        let server = mock_server(vec![
+            Scripted::Respond(500, vec![]),
+            Scripted::Respond(502, vec![]),
+        ])
+        .await;
+
+        let response = send(&retry_policy(3), &server).await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(server.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_server_errors_after_max_attempts() {
+        let server = mock_server(vec![
+            Scripted::Respond(503, vec![]),
+            Scripted::Respond(503, vec![]),
+            Scripted::Respond(503, vec![]),
+        ])
+        .await;
+
+        let error = send(&retry_policy(2), &server).await.unwrap_err();
+
+        assert!(matches!(error, PublishError::Transient { .. }));
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = mock_server(vec![Scripted::Respond(422, vec![])]).await;
+
+        let error = send(&retry_policy(3), &server).await.unwrap_err();
+
+        assert!(error.is_permanent());
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_timeouts() {
+        let server = mock_server(vec![Scripted::Hang]).await;
+
+        let response = send(&retry_policy(3), &server).await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(server.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_close_rate_limit_reset() {
+        let server = mock_server(vec![Scripted::Respond(
+            429,
+            vec![("Retry-After", "1".to_string())],
+        )])
+        .await;
+
+        let started = Instant::now();
+        let response = send(&retry_policy(3), &server).await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(server.requests(), 2);
+        assert!(started.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn defers_a_distant_rate_limit_reset() {
+        let reset = chrono::Utc::now() + chrono::Duration::minutes(5);
+        let server = mock_server(vec![Scripted::Respond(
+            429,
+            vec![
+                ("X-RateLimit-Remaining", "0".to_string()),
+                ("X-RateLimit-Reset", reset.to_rfc3339()),
+            ],
+        )])
+        .await;
+        let retry_policy = retry_policy(3);
+
+        let error = send(&retry_policy, &server).await.unwrap_err();
+
+        assert!(error.is_rate_limited());
+        assert!(!error.is_permanent());
+        assert_eq!(server.requests(), 1);
+
+        // The next request isn't sent until the rate limit resets.
+        let error = send(&retry_policy, &server).await.unwrap_err();
+
+        assert!(error.is_rate_limited());
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn honours_an_exhausted_rate_limit_on_success() {
+        let reset = chrono::Utc::now() + chrono::Duration::minutes(5);
+        let server = mock_server(vec![Scripted::Respond(
+            200,
+            vec![
+                ("X-RateLimit-Remaining", "0".to_string()),
+                ("X-RateLimit-Reset", reset.to_rfc3339()),
+            ],
+        )])
+        .await;
+        let retry_policy = retry_policy(3);
+
+        send(&retry_policy, &server).await.unwrap();
+        let error = send(&retry_policy, &server).await.unwrap_err();
+
+        assert!(error.is_rate_limited());
+        assert_eq!(server.requests(), 1);
+    }
+
+    #[tokio::test]
+    async fn honours_an_exhausted_rate_limit_on_server_errors() {
+        let reset = chrono::Utc::now() + chrono::Duration::seconds(1);
+        let server = mock_server(vec![Scripted::Respond(
+            503,
+            vec![
+                ("X-RateLimit-Remaining", "0".to_string()),
+                ("X-RateLimit-Reset", reset.to_rfc3339()),
+            ],
+        )])
+        .await;
+
+        let started = Instant::now();
+        let response = send(&retry_policy(3), &server).await.unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        assert_eq!(server.requests(), 2);
+        assert!(started.elapsed() >= Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,766 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serenity::{
+    all::{ChannelId, Colour, CreateEmbed, CreateEmbedFooter, CreateMessage},
+    http::Http,
+};
+use sqlx::PgPool;
+use tokio::sync::{Notify, RwLock};
+
+use crate::{
+    config::{EmptyQueueFallback, SchedulerConfig, SubmissionsConfig},
+    discord::{ModerationEvent, ModerationLog},
+    media::YuriMedia,
+    models::{
+        audit_log::{AddAuditEntry, AuditAction, AuditEntry, AuditEntryHelpers},
+        images::{Image, ImageHelpers, UpdateImageFiles, MAX_SUBMISSION_IMAGES},
+        posts::{AddPost, Post, PostHelpers, PostState, UpdatePost},
+        reposts::{Repost, RepostHelpers},
+        scheduler_state::{
+            SchedulerNotice, SchedulerState, SchedulerStateHelpers, SchedulerStatus,
+            UpdateSchedulerState,
+        },
+        submissions::{Submission, SubmissionHelpers, SubmissionIds},
+    },
+    publisher::{Publication, PublishError, Published, Publisher},
+    shutdown::Shutdown,
+};
+
+use self::schedule::Schedule;
+
+pub mod schedule;
+
+const EMPTY_QUEUE_STATUS: &str =
+    "We're out of Yuri for now! Submit some to us so we can keep posting every hour!";
+
+/// Hashtags every post ends with.
+pub const POST_TAGS: &[&str] = &["yuri"];
+
+pub struct YuriScheduler {
+    config: SchedulerConfig,
+    submissions_config: SubmissionsConfig,
+    schedule: Schedule,
+    clock: Arc<SchedulerClock>,
+    database: PgPool,
+    publishers: Vec<Box<dyn Publisher>>,
+    media: YuriMedia,
+    reviewers: ReviewerChannel,
+}
+
+/// The channel reviewers are told about problems with the queue in.
+pub struct ReviewerChannel {
+    pub http: Arc<Http>,
+    pub channel_id: ChannelId,
+    pub moderation_log: ModerationLog,
+}
+
+#[derive(Default)]
+pub struct SchedulerClock {
+    pub next_post: RwLock<Option<DateTime<Utc>>>,
+    pub last_tick: RwLock<Option<DateTime<Utc>>>,
+    /// Notified whenever a submission finishes posting.
+    pub posted: Notify,
+}
+
+impl YuriScheduler {
+    pub fn new(
+        config: SchedulerConfig,
+        submissions_config: SubmissionsConfig,
+        clock: Arc<SchedulerClock>,
+        database: PgPool,
+        publishers: Vec<Box<dyn Publisher>>,
+        media: YuriMedia,
+        reviewers: ReviewerChannel,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            schedule: Schedule::new(&config)?,
+            config,
+            submissions_config,
+            clock,
+            database,
+            publishers,
+            media,
+            reviewers,
+        })
+    }
+
+    /// Runs until a shutdown, a post that's already being published is
+    /// finished first.
+    pub async fn spawn(&self, shutdown: &Shutdown) -> anyhow::Result<()> {
+        debug!("spawning posting scheduler");
+
+        if let Err(error) = self.reconcile_posts().await {
+            error!("an error occurred while reconciling unfinished posts: {error:#?}");
+        }
+
+        let mut last_post = Utc::now();
+        loop {
+            let Some(next_post) = self.schedule.next_after(last_post.max(Utc::now())) else {
+                warn!("posting schedule has no upcoming posts, stopping the posting scheduler");
+                *self.clock.next_post.write().await = None;
+
+                return Ok(());
+            };
+
+            debug!("next post is scheduled at {next_post}");
+            *self.clock.next_post.write().await = Some(next_post);
+
+            tokio::select! {
+                () = tokio::time::sleep((next_post - Utc::now()).to_std().unwrap_or_default()) => {}
+                () = shutdown.triggered() => {
+                    debug!("stopping posting scheduler");
+                    return Ok(());
+                }
+            }
+            last_post = next_post;
+
+            if let Err(error) = self.tick().await {
+                error!("an error occurred while running the posting scheduler: {error:#?}");
+            }
+            *self.clock.last_tick.write().await = Some(Utc::now());
+        }
+    }
+
+    async fn tick(&self) -> anyhow::Result<()> {
+        let scheduler_state = SchedulerState::get_scheduler_state(&self.database).await?;
+        if scheduler_state.status == SchedulerStatus::Paused {
+            info!(
+                "posting scheduler is paused, skipping: {reason}",
+                reason = scheduler_state
+                    .reason
+                    .as_deref()
+                    .unwrap_or("no reason provided")
+            );
+            return Ok(());
+        }
+
+        if let Some(submission) = Submission::get_next_queued_submission(
+            &self.database,
+            self.submissions_config.require_alt_text,
+            &self.targets(),
+        )
+        .await?
+        {
+            self.publish_submission(&submission, false).await?;
+        } else {
+            info!("no queued submissions left to post");
+            self.handle_empty_queue(&scheduler_state).await?;
+        }
+
+        self.check_low_queue(&scheduler_state).await?;
+
+        Ok(())
+    }
+
+    /// Targets a submission has to reach before it leaves the queue.
+    fn targets(&self) -> Vec<String> {
+        self.publishers
+            .iter()
+            .map(|publisher| publisher.target().to_string())
+            .collect()
+    }
+
+    async fn handle_empty_queue(&self, scheduler_state: &SchedulerState) -> anyhow::Result<()> {
+        match self.config.empty_queue {
+            EmptyQueueFallback::Skip => {
+                debug!("skipping this post");
+            }
+            EmptyQueueFallback::Archive => {
+                let Some(submission) = Submission::get_archived_submission(
+                    &self.database,
+                    self.config.repost_gap_days,
+                )
+                .await?
+                else {
+                    info!("no archived submissions are available to repost, skipping");
+                    return Ok(());
+                };
+
+                self.publish_submission(&submission, true).await?;
+            }
+            EmptyQueueFallback::Status => {
+                if !is_notice_due(scheduler_state.empty_queue_notice_date) {
+                    debug!("empty queue status was already posted today, skipping");
+                    return Ok(());
+                }
+
+                let publication = Publication {
+                    text: String::from(EMPTY_QUEUE_STATUS),
+                    source_link: None,
+                    media_ids: vec![],
+                    content_warning: None,
+                    sensitive: false,
+                    repost: false,
+                };
+                let mut posted = false;
+                for publisher in &self.publishers {
+                    match publisher.publish(&publication, uuid::Uuid::now_v7()).await {
+                        Ok(_) => posted = true,
+                        Err(error) => error!(
+                            "an error occurred while posting the empty queue status to {target}: {error:#?}",
+                            target = publisher.target()
+                        ),
+                    }
+                }
+
+                // It's tried again on the next tick if it didn't go out
+                // anywhere.
+                if posted {
+                    SchedulerState::mark_scheduler_notice(
+                        &self.database,
+                        SchedulerNotice::EmptyQueue,
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_low_queue(&self, scheduler_state: &SchedulerState) -> anyhow::Result<()> {
+        let Some(low_queue_threshold) = self.config.low_queue_threshold else {
+            return Ok(());
+        };
+
+        let queued_submissions = Submission::count_queued_submissions(
+            &self.database,
+            self.submissions_config.require_alt_text,
+            &self.targets(),
+        )
+        .await?;
+        if queued_submissions >= low_queue_threshold
+            || !is_notice_due(scheduler_state.low_queue_warning_date)
+        {
+            return Ok(());
+        }
+
+        info!("only {queued_submissions} queued submissions are left, warning reviewers");
+
+        self.reviewers
+            .channel_id
+            .send_message(
+                &self.reviewers.http,
+                CreateMessage::new().embed(
+                    CreateEmbed::new()
+                        .title("The queue is running low!")
+                        .description(format!(
+                            "Only {queued_submissions} approved submissions are left to post."
+                        ))
+                        .colour(Colour::ORANGE),
+                ),
+            )
+            .await?;
+        SchedulerState::mark_scheduler_notice(&self.database, SchedulerNotice::LowQueue).await?;
+
+        Ok(())
+    }
+
+    /// Publishes a submission to every target it wasn't published to yet,
+    /// and marks it as posted once all of them are done with it.
+    async fn publish_submission(
+        &self,
+        submission: &Submission,
+        repost: bool,
+    ) -> anyhow::Result<()> {
+        debug!(
+            "publishing a submission with: `submission_id`: {submission_id}",
+            submission_id = submission.submission_id
+        );
+
+        // A submission stays queued until every target is done with it, so
+        // the targets it already made it to are skipped when it's retried.
+        let finished_targets = if repost {
+            vec![]
+        } else {
+            Post::get_finished_posts(&self.database, submission.submission_id)
+                .await?
+                .into_iter()
+                .map(|post| post.target)
+                .collect()
+        };
+
+        let mut published = false;
+        for publisher in &self.publishers {
+            if finished_targets
+                .iter()
+                .any(|target| target == publisher.target())
+            {
+                continue;
+            }
+
+            // A failed attempt may have been published anyway, so it's
+            // looked for before it's retried.
+            if let Some(failed_post) = Post::get_failed_post(
+                &self.database,
+                submission.submission_id,
+                repost,
+                publisher.target(),
+            )
+            .await?
+            {
+                match publisher.find_published(&failed_post.media_ids).await {
+                    Ok(Some(found_post)) => {
+                        self.record_published_post(&failed_post, found_post)
+                            .await?;
+                        published = true;
+                        continue;
+                    }
+                    Ok(None) => {}
+                    // The retry is still sent with the same idempotency key.
+                    Err(error) => error!(
+                        "an error occurred while looking for a failed post attempt with `post_id`: {post_id}: {error:#?}",
+                        post_id = failed_post.post_id
+                    ),
+                }
+            }
+
+            let post = Post::add_post(
+                &self.database,
+                AddPost {
+                    submission_id: submission.submission_id,
+                    target: publisher.target().to_string(),
+                    repost,
+                },
+            )
+            .await?;
+
+            match self
+                .publish_post(publisher.as_ref(), submission, &post)
+                .await
+            {
+                Ok(()) => published = true,
+                Err(error) => {
+                    error!(
+                        "an error occurred while publishing to {target}: {error:#?}",
+                        target = publisher.target()
+                    );
+                    self.record_failed_post(submission, &post, &error).await?;
+                }
+            }
+        }
+
+        if repost {
+            if published {
+                Repost::add_repost(&self.database, submission.submission_id).await?;
+            }
+        } else {
+            self.complete_submission(submission.submission_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Marks a submission as posted once every target published it or gave
+    /// up on it, as long as it was published somewhere.
+    async fn complete_submission(&self, submission_id: i32) -> anyhow::Result<()> {
+        let finished_posts = Post::get_finished_posts(&self.database, submission_id).await?;
+
+        let all_finished = self.publishers.iter().all(|publisher| {
+            finished_posts
+                .iter()
+                .any(|post| post.target == publisher.target())
+        });
+        let published_anywhere = finished_posts
+            .iter()
+            .any(|post| post.state == PostState::Published);
+
+        if all_finished && published_anywhere {
+            Submission::mark_submission_posted(
+                &self.database,
+                SubmissionIds::SubmissionId(submission_id),
+            )
+            .await?;
+
+            info!("finished posting a submission with: `submission_id`: {submission_id}");
+            self.clock.posted.notify_one();
+        }
+
+        Ok(())
+    }
+
+    async fn record_failed_post(
+        &self,
+        submission: &Submission,
+        post: &Post,
+        error: &anyhow::Error,
+    ) -> anyhow::Result<()> {
+        // Running out of the rate limit isn't the post's fault, so it's just
+        // tried again on a later tick without counting towards giving up.
+        if error
+            .downcast_ref::<PublishError>()
+            .is_some_and(PublishError::is_rate_limited)
+        {
+            Post::update_post(
+                &self.database,
+                post.post_id,
+                UpdatePost::Deferred {
+                    error: format!("{error:#}"),
+                },
+            )
+            .await?;
+            metrics::counter!(
+                "yuri_posts_total",
+                "target" => post.target.clone(),
+                "state" => "deferred"
+            )
+            .increment(1);
+
+            warn!(
+                "deferred posting a submission until the rate limit resets with: `submission_id`: {submission_id}, `target`: {target}",
+                submission_id = submission.submission_id,
+                target = post.target
+            );
+            return Ok(());
+        }
+
+        let permanent_failure = error
+            .downcast_ref::<PublishError>()
+            .is_some_and(PublishError::is_permanent);
+        let failed_posts = Post::count_failed_posts(
+            &self.database,
+            post.submission_id,
+            post.repost,
+            &post.target,
+        )
+        .await?
+            + 1;
+
+        if !permanent_failure && failed_posts < self.config.dead_letter_after {
+            Post::update_post(
+                &self.database,
+                post.post_id,
+                UpdatePost::Failed {
+                    error: format!("{error:#}"),
+                },
+            )
+            .await?;
+            metrics::counter!(
+                "yuri_posts_total",
+                "target" => post.target.clone(),
+                "state" => "failed"
+            )
+            .increment(1);
+
+            self.reviewers
+                .moderation_log
+                .log(ModerationEvent::PostFailed {
+                    submission_id: submission.submission_id,
+                    target: post.target.clone(),
+                    error: format!("{error:#}"),
+                    alert: None,
+                })
+                .await;
+
+            return Ok(());
+        }
+
+        Post::update_post(
+            &self.database,
+            post.post_id,
+            UpdatePost::Dead {
+                error: format!("{error:#}"),
+            },
+        )
+        .await?;
+        metrics::counter!(
+            "yuri_posts_total",
+            "target" => post.target.clone(),
+            "state" => "dead"
+        )
+        .increment(1);
+
+        warn!(
+            "gave up on posting a submission with: `submission_id`: {submission_id}, `target`: {target}",
+            submission_id = submission.submission_id,
+            target = post.target
+        );
+
+        let alert = self
+            .reviewers
+            .channel_id
+            .send_message(
+                &self.reviewers.http,
+                CreateMessage::new().embed(
+                    CreateEmbed::new()
+                        .title(format!("A post to {target} keeps failing!", target = post.target))
+                        .description(format!(
+                            "Gave up on posting after {failed_posts} failed attempts, it has been removed from the queue.\n```\n{error}\n```",
+                            error = format!("{error:#}").chars().take(1000).collect::<String>()
+                        ))
+                        .fields(vec![
+                            ("Artist", submission.artist.clone(), true),
+                            ("Art Link", submission.art_link.clone(), true),
+                        ])
+                        .footer(CreateEmbedFooter::new(format!(
+                            "Submission Id: {submission_id}",
+                            submission_id = submission.submission_id
+                        )))
+                        .colour(Colour::RED),
+                ),
+            )
+            .await?;
+
+        self.reviewers
+            .moderation_log
+            .log(ModerationEvent::PostFailed {
+                submission_id: submission.submission_id,
+                target: post.target.clone(),
+                error: format!("{error:#}"),
+                alert: Some((alert.channel_id, alert.id)),
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn publish_post(
+        &self,
+        publisher: &dyn Publisher,
+        submission: &Submission,
+        post: &Post,
+    ) -> anyhow::Result<()> {
+        let images = Image::get_submission_images(&self.database, submission.submission_id).await?;
+
+        let mut media_ids = vec![];
+        for image in images.iter().take(MAX_SUBMISSION_IMAGES) {
+            let prepared_media = self.media.prepare(image).await?;
+            Image::update_image_files(
+                &self.database,
+                image.image_id,
+                UpdateImageFiles {
+                    original_path: prepared_media.original_path,
+                    processed_path: prepared_media.processed_path,
+                },
+            )
+            .await?;
+
+            media_ids.push(
+                publisher
+                    .upload_media(&prepared_media.media, image.alt_text.as_deref())
+                    .await?,
+            );
+        }
+
+        // The post is only ever created once the attempt is recorded as
+        // uploaded, so a `pending` attempt found on startup is known to have
+        // never reached the target.
+        let post = Post::update_post(
+            &self.database,
+            post.post_id,
+            UpdatePost::Uploaded { media_ids },
+        )
+        .await?;
+
+        self.finish_post(publisher, submission, &post).await
+    }
+
+    async fn finish_post(
+        &self,
+        publisher: &dyn Publisher,
+        submission: &Submission,
+        post: &Post,
+    ) -> anyhow::Result<()> {
+        let published = publisher
+            .publish(
+                &Publication {
+                    text: post_text(submission, post.repost),
+                    source_link: Some(submission.art_link.clone()),
+                    media_ids: post.media_ids.clone(),
+                    content_warning: submission.content_warning.clone(),
+                    sensitive: submission.content_warning.is_some(),
+                    repost: post.repost,
+                },
+                post.idempotency_key,
+            )
+            .await?;
+
+        self.record_published_post(post, published).await
+    }
+
+    async fn record_published_post(&self, post: &Post, published: Published) -> anyhow::Result<()> {
+        Post::update_post(
+            &self.database,
+            post.post_id,
+            UpdatePost::Published {
+                status_id: published.id.clone(),
+                status_url: published.url.clone(),
+            },
+        )
+        .await?;
+        metrics::counter!(
+            "yuri_posts_total",
+            "target" => post.target.clone(),
+            "state" => "published"
+        )
+        .increment(1);
+
+        info!(
+            "published a submission with: `submission_id`: {submission_id}, `target`: {target}, `status_id`: {status_id}",
+            submission_id = post.submission_id,
+            target = post.target,
+            status_id = published.id
+        );
+
+        self.reviewers
+            .moderation_log
+            .log(ModerationEvent::Published {
+                submission_id: post.submission_id,
+                target: post.target.clone(),
+                status_url: published.url,
+            })
+            .await;
+
+        Ok(())
+    }
+
+    async fn reconcile_posts(&self) -> anyhow::Result<()> {
+        let unfinished_posts = Post::get_unfinished_posts(&self.database).await?;
+        if unfinished_posts.is_empty() {
+            debug!("no unfinished post attempts found to reconcile");
+            return Ok(());
+        }
+
+        info!(
+            "reconciling {count} unfinished post attempts",
+            count = unfinished_posts.len()
+        );
+
+        for post in unfinished_posts {
+            let Some(publisher) = self
+                .publishers
+                .iter()
+                .find(|publisher| publisher.target() == post.target)
+            else {
+                Post::update_post(
+                    &self.database,
+                    post.post_id,
+                    UpdatePost::Failed {
+                        error: format!("`{target}` isn't configured anymore", target = post.target),
+                    },
+                )
+                .await?;
+                continue;
+            };
+
+            if post.state == PostState::Pending {
+                Post::update_post(
+                    &self.database,
+                    post.post_id,
+                    UpdatePost::Failed {
+                        error: String::from("interrupted before the media upload finished"),
+                    },
+                )
+                .await?;
+                continue;
+            }
+
+            // An attempt with media can be matched against what was actually
+            // published, otherwise it's sent again with the same idempotency
+            // key and targets that support it return the existing post.
+            let result = match publisher.find_published(&post.media_ids).await {
+                Ok(Some(published)) => self.record_published_post(&post, published).await,
+                Ok(None) => {
+                    let submission = Submission::get_submission(
+                        &self.database,
+                        SubmissionIds::SubmissionId(post.submission_id),
+                    )
+                    .await?;
+
+                    self.finish_post(publisher.as_ref(), &submission, &post)
+                        .await
+                }
+                Err(error) => Err(error),
+            };
+
+            match result {
+                Ok(()) if post.repost => {
+                    Repost::add_repost(&self.database, post.submission_id).await?;
+                }
+                Ok(()) => self.complete_submission(post.submission_id).await?,
+                Err(error) => {
+                    error!(
+                        "an error occurred while reconciling a post attempt with `post_id`: {post_id}: {error:#?}",
+                        post_id = post.post_id
+                    );
+
+                    Post::update_post(
+                        &self.database,
+                        post.post_id,
+                        UpdatePost::Failed {
+                            error: format!("{error:#}"),
+                        },
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Pauses or resumes posting, recording the change in the audit log.
+pub async fn change_scheduler_state(
+    database: &PgPool,
+    update_scheduler_state: UpdateSchedulerState,
+) -> anyhow::Result<SchedulerState> {
+    let action = match update_scheduler_state.status {
+        SchedulerStatus::Paused => AuditAction::PausePosting,
+        SchedulerStatus::Running => AuditAction::ResumePosting,
+    };
+    let actor_id = update_scheduler_state.changed_by;
+
+    let mut tx = database.begin().await?;
+    let previous_state = SchedulerState::get_scheduler_state(&mut *tx).await?;
+    let scheduler_state =
+        SchedulerState::update_scheduler_state(&mut *tx, update_scheduler_state).await?;
+    AuditEntry::add_audit_entry(
+        &mut *tx,
+        AddAuditEntry {
+            actor_id,
+            action,
+            submission_id: None,
+            before_value: Some(scheduler_state_value(&previous_state)),
+            after_value: Some(scheduler_state_value(&scheduler_state)),
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    Ok(scheduler_state)
+}
+
+fn scheduler_state_value(scheduler_state: &SchedulerState) -> String {
+    match (scheduler_state.status, &scheduler_state.reason) {
+        (SchedulerStatus::Running, _) => String::from("running"),
+        (SchedulerStatus::Paused, Some(reason)) => format!("paused: {reason}"),
+        (SchedulerStatus::Paused, None) => String::from("paused"),
+    }
+}
+
+/// Text of a post, also used by the feed so it matches what went out.
+pub fn post_text(submission: &Submission, repost: bool) -> String {
+    format!(
+        "{archive_note}Artist: {artist}\nSource: {art_link}\n\n{tags}",
+        archive_note = if repost { "From the archive!\n\n" } else { "" },
+        artist = submission.artist,
+        art_link = submission.art_link,
+        tags = POST_TAGS
+            .iter()
+            .map(|tag| format!("#{tag}"))
+            .collect::<Vec<_>>()
+            .join(" ")
+    )
+}
+
+fn is_notice_due(last_notice_date: Option<chrono::NaiveDateTime>) -> bool {
+    last_notice_date.map_or(true, |last_notice_date| {
+        chrono::Utc::now()
+            .naive_utc()
+            .signed_duration_since(last_notice_date)
+            .num_days()
+            >= 1
+    })
+}
@@ -0,0 +1,167 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Days, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::config::{BlackoutWindow, PostSchedule, SchedulerConfig};
+
+// Upper bound on how many candidate fire times are checked against the
+// blackout windows before giving up, so a schedule that is blacked out
+// entirely can't spin forever.
+const MAX_CANDIDATES: usize = 10_000;
+
+// Daylight saving gaps are an hour at most nowadays, but a timezone has
+// skipped a whole day before.
+const MAX_GAP_MINUTES: i64 = 24 * 60;
+
+pub struct Schedule {
+    kind: ScheduleKind,
+    timezone: Tz,
+    blackouts: Vec<BlackoutWindow>,
+}
+
+enum ScheduleKind {
+    Cron(Box<cron::Schedule>),
+    Times(Vec<NaiveTime>),
+}
+
+impl Schedule {
+    pub fn new(scheduler_config: &SchedulerConfig) -> anyhow::Result<Self> {
+        let kind = match &scheduler_config.schedule {
+            PostSchedule::Cron(expression) => {
+                // The cron crate wants a seconds field, which the usual five
+                // field expressions don't have.
+                let normalized = if expression.split_whitespace().count() == 5 {
+                    format!("0 {expression}")
+                } else {
+                    expression.clone()
+                };
+
+                ScheduleKind::Cron(Box::new(cron::Schedule::from_str(&normalized).map_err(
+                    |error| {
+                        anyhow::anyhow!(
+                            "invalid cron expression `{expression}`, expected 5 fields or 6 to 7 with seconds first and an optional year last: {error}"
+                        )
+                    },
+                )?))
+            }
+            PostSchedule::Times(times) => {
+                if times.is_empty() {
+                    anyhow::bail!("posting schedule has no times configured");
+                }
+
+                let mut times = times.clone();
+                times.sort_unstable();
+                times.dedup();
+                ScheduleKind::Times(times)
+            }
+        };
+
+        Ok(Self {
+            kind,
+            timezone: scheduler_config.timezone,
+            blackouts: scheduler_config.blackouts.clone(),
+        })
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let after = after.with_timezone(&self.timezone);
+
+        let candidates: Box<dyn Iterator<Item = DateTime<Tz>>> = match &self.kind {
+            ScheduleKind::Cron(schedule) => Box::new(schedule.after(&after)),
+            ScheduleKind::Times(times) => Box::new(
+                (0..)
+                    .map_while(move |day| after.date_naive().checked_add_days(Days::new(day)))
+                    .flat_map(move |date| {
+                        times
+                            .iter()
+                            .filter_map(move |time| self.resolve_local(date.and_time(*time)))
+                    })
+                    .filter(move |candidate| *candidate > after),
+            ),
+        };
+
+        candidates
+            .take(MAX_CANDIDATES)
+            .find(|candidate| !self.is_blacked_out(candidate.time()))
+            .map(|candidate| candidate.with_timezone(&Utc))
+    }
+
+    /// Times that fall in a daylight saving gap don't exist, so they're
+    /// posted at as soon as the clocks have gone forward rather than skipped.
+    fn resolve_local(&self, datetime: NaiveDateTime) -> Option<DateTime<Tz>> {
+        (0..=MAX_GAP_MINUTES).find_map(|minutes| {
+            self.timezone
+                .from_local_datetime(&(datetime + TimeDelta::minutes(minutes)))
+                .earliest()
+        })
+    }
+
+    fn is_blacked_out(&self, time: NaiveTime) -> bool {
+        self.blackouts.iter().any(|blackout| {
+            if blackout.start <= blackout.end {
+                blackout.start <= time && time < blackout.end
+            } else {
+                blackout.start <= time || time < blackout.end
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, NaiveTime};
+
+    use super::*;
+
+    fn schedule(schedule: PostSchedule, timezone: Tz) -> Schedule {
+        Schedule::new(&SchedulerConfig {
+            schedule,
+            timezone,
+            ..SchedulerConfig::default()
+        })
+        .unwrap()
+    }
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .and_then(|date| date.and_hms_opt(hour, minute, 0))
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn five_field_cron_runs_at_the_start_of_the_minute() {
+        let schedule = schedule(PostSchedule::Cron(String::from("30 * * * *")), Tz::UTC);
+
+        assert_eq!(
+            schedule.next_after(utc(2024, 3, 10, 12, 0)),
+            Some(utc(2024, 3, 10, 12, 30))
+        );
+    }
+
+    #[test]
+    fn six_field_cron_keeps_its_seconds() {
+        let schedule = schedule(PostSchedule::Cron(String::from("15 30 * * * *")), Tz::UTC);
+
+        assert_eq!(
+            schedule.next_after(utc(2024, 3, 10, 12, 0)),
+            Some(utc(2024, 3, 10, 12, 30) + TimeDelta::seconds(15))
+        );
+    }
+
+    #[test]
+    fn times_in_a_daylight_saving_gap_post_once_it_ends() {
+        // Clocks in New York went from 02:00 straight to 03:00 that night.
+        let schedule = schedule(
+            PostSchedule::Times(vec![NaiveTime::from_hms_opt(2, 30, 0).unwrap()]),
+            Tz::America__New_York,
+        );
+
+        // 03:00 EDT, rather than 02:30 the day after.
+        assert_eq!(
+            schedule.next_after(utc(2024, 3, 10, 5, 0)),
+            Some(utc(2024, 3, 10, 7, 0))
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::{atomic::Ordering, Arc};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use chrono::Utc;
+use metrics_exporter_prometheus::PrometheusHandle;
+use serde_json::{json, Value};
+use sqlx::PgPool;
+
+use crate::{
+    config::{ServerConfig, SubmissionsConfig},
+    discord::GatewayStatus,
+    models::{
+        posts::{Post, PostHelpers},
+        submissions::{Submission, SubmissionHelpers},
+        votes::{Vote, VoteHelpers},
+    },
+    scheduler::SchedulerClock,
+    shutdown::Shutdown,
+};
+
+mod prometheus;
+
+pub struct YuriServer {
+    address: std::net::SocketAddr,
+    state: Arc<ServerState>,
+}
+
+struct ServerState {
+    config: ServerConfig,
+    submissions_config: SubmissionsConfig,
+    targets: Vec<String>,
+    database: PgPool,
+    metrics: PrometheusHandle,
+    scheduler_clock: Arc<SchedulerClock>,
+    gateway_status: Arc<GatewayStatus>,
+}
+
+impl YuriServer {
+    /// Also installs the metrics recorder, metrics are only recorded while
+    /// there's a server to scrape them from.
+    pub fn new(
+        server_config: ServerConfig,
+        submissions_config: SubmissionsConfig,
+        targets: Vec<String>,
+        database: PgPool,
+        scheduler_clock: Arc<SchedulerClock>,
+        gateway_status: Arc<GatewayStatus>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            address: server_config.address,
+            state: ServerState {
+                config: server_config,
+                submissions_config,
+                targets,
+                database,
+                metrics: prometheus::install()?,
+                scheduler_clock,
+                gateway_status,
+            }
+            .into(),
+        })
+    }
+
+    pub async fn spawn(&self, shutdown: &Shutdown) -> anyhow::Result<()> {
+        let router = Router::new()
+            .route("/healthz", get(healthz))
+            .route("/readyz", get(readyz))
+            .route("/metrics", get(metrics))
+            .with_state(self.state.clone());
+
+        let listener = tokio::net::TcpListener::bind(self.address).await?;
+
+        debug!("spawning HTTP server on {address}", address = self.address);
+        let shutdown = shutdown.clone();
+        axum::serve(listener, router)
+            .with_graceful_shutdown(async move { shutdown.triggered().await })
+            .await?;
+
+        Ok(())
+    }
+}
+
+async fn healthz() -> &'static str {
+    "ok"
+}
+
+async fn readyz(State(state): State<Arc<ServerState>>) -> (StatusCode, Json<Value>) {
+    let now = Utc::now();
+
+    let gateway_connected = state.gateway_status.connected.load(Ordering::Relaxed);
+
+    // The scheduler sleeps until its next post, so it's only behind once
+    // that's overdue.
+    let next_post = *state.scheduler_clock.next_post.read().await;
+    let last_tick = *state.scheduler_clock.last_tick.read().await;
+    let scheduler_fresh = next_post.is_some_and(|next_post| {
+        (now - next_post).num_seconds()
+            < state.config.tick_grace_secs.try_into().unwrap_or(i64::MAX)
+    });
+
+    let (database_healthy, last_post_fresh, last_post) =
+        match Post::get_last_publish_date(&state.database).await {
+            Ok(last_post) => {
+                let last_post = last_post.map(|last_post| last_post.and_utc());
+                let last_post_fresh = state.config.max_post_age_secs.map_or(true, |max_post_age| {
+                    last_post.is_some_and(|last_post| {
+                        (now - last_post).num_seconds()
+                            < max_post_age.try_into().unwrap_or(i64::MAX)
+                    })
+                });
+
+                (true, last_post_fresh, last_post)
+            }
+            Err(error) => {
+                error!("an error occurred while checking the database: {error:#?}");
+                (false, false, None)
+            }
+        };
+
+    let ready = gateway_connected && database_healthy && scheduler_fresh && last_post_fresh;
+
+    (
+        if ready {
+            StatusCode::OK
+        } else {
+            StatusCode::SERVICE_UNAVAILABLE
+        },
+        Json(json!({
+            "ready": ready,
+            "gateway": { "connected": gateway_connected },
+            "database": { "healthy": database_healthy },
+            "scheduler": {
+                "fresh": scheduler_fresh,
+                "last_tick": last_tick,
+                "next_post": next_post,
+            },
+            "last_post": {
+                "fresh": last_post_fresh,
+                "publish_date": last_post,
+            },
+        })),
+    )
+}
+
+async fn metrics(State(state): State<Arc<ServerState>>) -> (StatusCode, String) {
+    // Gauges that are read from the database on every scrape, so they never
+    // drift from it.
+    let gauges = async {
+        let queue_depth = Submission::count_queued_submissions(
+            &state.database,
+            state.submissions_config.require_alt_text,
+            &state.targets,
+        )
+        .await?;
+        let vote_counts = Vote::count_votes(&state.database).await?;
+
+        metrics::gauge!("yuri_queue_depth").set(queue_depth as f64);
+        metrics::gauge!("yuri_votes", "vote" => "up").set(vote_counts.upvotes as f64);
+        metrics::gauge!("yuri_votes", "vote" => "down").set(vote_counts.downvotes as f64);
+
+        anyhow::Ok(())
+    };
+
+    if let Err(error) = gauges.await {
+        error!("an error occurred while reading metrics from the database: {error:#?}");
+        return (StatusCode::SERVICE_UNAVAILABLE, String::new());
+    }
+
+    (StatusCode::OK, state.metrics.render())
+}
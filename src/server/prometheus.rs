@@ -0,0 +1,49 @@
+use metrics::{describe_counter, describe_gauge, describe_histogram, Unit};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+// Mastodon can take a while to process media, so the buckets go up to its
+// request timeout.
+const MASTODON_LATENCY_BUCKETS: &[f64] = &[0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+pub fn install() -> anyhow::Result<PrometheusHandle> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(String::from("yuri_mastodon_request_duration_seconds")),
+            MASTODON_LATENCY_BUCKETS,
+        )?
+        .install_recorder()?;
+
+    describe_counter!("yuri_submissions_total", "Submissions sent for review.");
+    describe_counter!(
+        "yuri_submission_decisions_total",
+        "Submissions approved or rejected by reviewers."
+    );
+    describe_counter!(
+        "yuri_reviewer_votes_total",
+        "Votes cast by reviewers on pending submissions."
+    );
+    describe_counter!(
+        "yuri_expired_approvals_total",
+        "Pending approvals removed after going without a decision."
+    );
+    describe_gauge!("yuri_votes", "Votes cast on submissions.");
+    describe_gauge!(
+        "yuri_queue_depth",
+        "Approved submissions waiting to be posted."
+    );
+    describe_counter!(
+        "yuri_posts_total",
+        "Post attempts that were published, failed, deferred by a rate limit, or given up on."
+    );
+    describe_histogram!(
+        "yuri_mastodon_request_duration_seconds",
+        Unit::Seconds,
+        "How long Mastodon took to respond to each request attempt."
+    );
+    describe_counter!(
+        "yuri_interaction_errors_total",
+        "Errors returned by interaction handlers."
+    );
+
+    Ok(handle)
+}
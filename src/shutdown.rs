@@ -0,0 +1,57 @@
+use tokio_util::{
+    sync::CancellationToken,
+    task::{task_tracker::TaskTrackerToken, TaskTracker},
+};
+
+/// Tells every part of the bot to stop, and keeps track of work that should
+/// finish before the process exits.
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    token: CancellationToken,
+    tracker: TaskTracker,
+}
+
+impl Shutdown {
+    pub fn trigger(&self) {
+        self.token.cancel();
+        self.tracker.close();
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.token.is_cancelled()
+    }
+
+    pub async fn triggered(&self) {
+        self.token.cancelled().await;
+    }
+
+    /// Marks work as in flight for as long as the returned token is alive.
+    /// Take it before checking [`Shutdown::is_triggered`], so the work can't
+    /// start after [`Shutdown::finished`] stopped waiting.
+    pub fn track(&self) -> TaskTrackerToken {
+        self.tracker.token()
+    }
+
+    /// Waits for the shutdown and for the work in flight to finish.
+    pub async fn finished(&self) {
+        self.tracker.wait().await;
+    }
+}
+
+/// Waits for SIGINT, or SIGTERM where there is one.
+pub async fn signal() -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result?,
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await?;
+
+    Ok(())
+}
@@ -0,0 +1,224 @@
+//! Decisions on the same submission racing each other, like two reviewers
+//! clicking **Approve** and **Reject** at once or a double click. They need a
+//! real database, so `DATABASE_URL` has to point at a Postgres server the
+//! tests can create databases on.
+
+use std::sync::Arc;
+
+use serenity::all::{GuildId, UserId};
+use sqlx::PgPool;
+use yuri_every_hour_bot::{
+    config::{DiscordChannelConfig, SubmissionsConfig},
+    discord::{
+        approvals::{decide_approval, ApprovalOutcome},
+        data::{PendingApprovalsHelpers, YuriData},
+        YuriConfig,
+    },
+    models::{
+        audit_log::{AuditEntry, AuditEntryHelpers},
+        images::{AddImages, Image, ImageHelpers},
+        pending_approvals::AddPendingApproval,
+        reviewer_votes::{ReviewerVote, ReviewerVoteHelpers},
+        submissions::{AddSubmission, Submission, SubmissionDecision, SubmissionHelpers},
+    },
+};
+
+// Races are run a few times, since a single one could happen to not overlap.
+const RACES: usize = 20;
+
+const TEAM: [u64; 3] = [1, 2, 3];
+
+fn config(approval_quorum: u32) -> YuriConfig {
+    YuriConfig {
+        channels: DiscordChannelConfig {
+            approve_id: 1,
+            vote_id: 2,
+            log_id: None,
+        },
+        submissions: SubmissionsConfig {
+            require_alt_text: false,
+            system_user_id: None,
+            approval_quorum,
+            rejection_quorum: 1,
+        },
+        targets: vec![String::from("mastodon")],
+        team: TEAM.into_iter().map(UserId::new).collect(),
+        admins: vec![],
+        server_id: GuildId::new(1),
+    }
+}
+
+/// Adds a submission with an approval message, giving the message's ID.
+async fn add_approval(database: &PgPool, data: &YuriData) -> anyhow::Result<(i32, i64)> {
+    let submission = Submission::add_submission(
+        database,
+        AddSubmission {
+            user_id: 1,
+            artist: String::from("Artist"),
+            art_link: String::from("https://example.com/art"),
+            additional_information: None,
+            content_warning: None,
+        },
+    )
+    .await?;
+    Image::add_images(
+        database,
+        AddImages {
+            submission_id: submission.submission_id,
+            image_paths: vec![String::from("https://example.com/art.png")],
+            alt_texts: vec![Some(String::from("Alt text"))],
+        },
+    )
+    .await?;
+
+    // There's no Discord message, the submission's ID stands in for it.
+    let message_id = u64::try_from(submission.submission_id)?;
+    data.add_pending_approval(
+        database,
+        AddPendingApproval {
+            submission_id: submission.submission_id,
+            message_id,
+        },
+    )
+    .await?;
+
+    Ok((submission.submission_id, i64::try_from(message_id)?))
+}
+
+/// Has every reviewer vote on the same approval at once, giving what came of
+/// each vote in the same order. Votes are the reviewer and whether they
+/// approve.
+async fn race(
+    database: &PgPool,
+    config: &Arc<YuriConfig>,
+    votes: &[(u64, bool)],
+) -> anyhow::Result<(i32, Vec<ApprovalOutcome>)> {
+    let data = Arc::new(YuriData::default());
+    let (submission_id, message_id) = add_approval(database, &data).await?;
+
+    let deciders = votes
+        .iter()
+        .map(|&(reviewer_id, approve)| {
+            let database = database.clone();
+            let data = data.clone();
+            let config = config.clone();
+            let reviewer_id = UserId::new(reviewer_id);
+            let decision = if approve {
+                SubmissionDecision::Approved
+            } else {
+                SubmissionDecision::Rejected
+            };
+
+            tokio::spawn(async move {
+                decide_approval(&database, &data, &config, message_id, reviewer_id, decision).await
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut outcomes = vec![];
+    for decider in deciders {
+        outcomes.push(decider.await??);
+    }
+
+    Ok((submission_id, outcomes))
+}
+
+async fn audit_entries(database: &PgPool, submission_id: i32) -> anyhow::Result<usize> {
+    Ok(AuditEntry::get_all_audit_entries(database)
+        .await?
+        .into_iter()
+        .filter(|audit_entry| audit_entry.submission_id == Some(submission_id))
+        .count())
+}
+
+/// The reviewer whose vote decided the submission, and whether every other
+/// reviewer who didn't get a vote in was told who decided.
+fn decider(votes: &[(u64, bool)], outcomes: &[ApprovalOutcome]) -> Option<(u64, bool)> {
+    let decided = outcomes
+        .iter()
+        .zip(votes)
+        .filter_map(|(outcome, (reviewer_id, _))| match outcome {
+            ApprovalOutcome::Decided { decision, .. } => Some((*reviewer_id, decision)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let &[(decider_id, decision)] = &decided[..] else {
+        return None;
+    };
+
+    let already_decided = format!(
+        "<@{decider_id}> already {decision} this submission.",
+        decision = match decision {
+            SubmissionDecision::Approved => "approved",
+            SubmissionDecision::Rejected => "rejected",
+        }
+    );
+    let losers_told = outcomes.iter().all(|outcome| match outcome {
+        ApprovalOutcome::Refused(reason) => *reason == already_decided,
+        ApprovalOutcome::Voted(_) | ApprovalOutcome::Decided { .. } => true,
+        ApprovalOutcome::Expired { .. } => false,
+    });
+
+    Some((decider_id, losers_told))
+}
+
+#[sqlx::test]
+async fn approve_and_reject_at_once_decide_once(database: PgPool) -> anyhow::Result<()> {
+    let config = Arc::new(config(1));
+    let votes = [(1, true), (2, false)];
+
+    for _ in 0..RACES {
+        let (submission_id, outcomes) = race(&database, &config, &votes).await?;
+
+        let (_, losers_told) = decider(&votes, &outcomes).expect("exactly one decision");
+        assert!(losers_told);
+        assert_eq!(audit_entries(&database, submission_id).await?, 1);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn double_approvals_decide_once(database: PgPool) -> anyhow::Result<()> {
+    let config = Arc::new(config(1));
+    let votes = [(1, true), (1, true)];
+
+    for _ in 0..RACES {
+        let (submission_id, outcomes) = race(&database, &config, &votes).await?;
+
+        assert_eq!(decider(&votes, &outcomes), Some((1, true)));
+        assert_eq!(audit_entries(&database, submission_id).await?, 1);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn quorum_votes_at_once_decide_once(database: PgPool) -> anyhow::Result<()> {
+    let config = Arc::new(config(2));
+    let votes = TEAM.map(|reviewer_id| (reviewer_id, true));
+
+    for _ in 0..RACES {
+        let (submission_id, outcomes) = race(&database, &config, &votes).await?;
+
+        let (_, losers_told) = decider(&votes, &outcomes).expect("exactly one decision");
+        assert!(losers_told);
+        assert_eq!(
+            outcomes
+                .iter()
+                .filter(|outcome| matches!(outcome, ApprovalOutcome::Voted(_)))
+                .count(),
+            1
+        );
+        // The reviewer who was too late doesn't get a vote in.
+        assert_eq!(
+            ReviewerVote::get_reviewer_votes(&database, submission_id)
+                .await?
+                .len(),
+            2
+        );
+        assert_eq!(audit_entries(&database, submission_id).await?, 1);
+    }
+
+    Ok(())
+}
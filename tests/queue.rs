@@ -0,0 +1,164 @@
+//! Which approved submissions the scheduler still has to post. They need a
+//! real database, so `DATABASE_URL` has to point at a Postgres server the
+//! tests can create databases on.
+
+use sqlx::PgPool;
+use yuri_every_hour_bot::models::{
+    images::{AddImages, Image, ImageHelpers},
+    posts::{AddPost, Post, PostHelpers, UpdatePost},
+    submissions::{AddSubmission, Submission, SubmissionHelpers, SubmissionIds},
+};
+
+fn targets() -> Vec<String> {
+    vec![String::from("mastodon"), String::from("bluesky")]
+}
+
+async fn add_approved_submission(database: &PgPool) -> anyhow::Result<i32> {
+    let submission = Submission::add_submission(
+        database,
+        AddSubmission {
+            user_id: 1,
+            artist: String::from("Artist"),
+            art_link: String::from("https://example.com/art"),
+            additional_information: None,
+            content_warning: None,
+        },
+    )
+    .await?;
+    Submission::approve_submission(
+        database,
+        SubmissionIds::SubmissionId(submission.submission_id),
+    )
+    .await?;
+
+    Ok(submission.submission_id)
+}
+
+async fn add_post(
+    database: &PgPool,
+    submission_id: i32,
+    target: &str,
+    update_post: UpdatePost,
+) -> anyhow::Result<()> {
+    let post = Post::add_post(
+        database,
+        AddPost {
+            submission_id,
+            target: target.to_string(),
+            repost: false,
+        },
+    )
+    .await?;
+    Post::update_post(database, post.post_id, update_post).await?;
+
+    Ok(())
+}
+
+async fn next_queued(database: &PgPool) -> anyhow::Result<Option<i32>> {
+    Ok(
+        Submission::get_next_queued_submission(database, false, &targets())
+            .await?
+            .map(|submission| submission.submission_id),
+    )
+}
+
+#[sqlx::test]
+async fn a_dead_target_keeps_the_others_queued(database: PgPool) -> anyhow::Result<()> {
+    let submission_id = add_approved_submission(&database).await?;
+    add_post(
+        &database,
+        submission_id,
+        "mastodon",
+        UpdatePost::Dead {
+            error: String::from("422 Unprocessable Entity"),
+        },
+    )
+    .await?;
+    add_post(
+        &database,
+        submission_id,
+        "bluesky",
+        UpdatePost::Failed {
+            error: String::from("502 Bad Gateway"),
+        },
+    )
+    .await?;
+
+    assert_eq!(next_queued(&database).await?, Some(submission_id));
+    assert_eq!(
+        Submission::count_queued_submissions(&database, false, &targets()).await?,
+        1
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn every_dead_target_leaves_the_queue(database: PgPool) -> anyhow::Result<()> {
+    let submission_id = add_approved_submission(&database).await?;
+    for target in targets() {
+        add_post(
+            &database,
+            submission_id,
+            &target,
+            UpdatePost::Dead {
+                error: String::from("422 Unprocessable Entity"),
+            },
+        )
+        .await?;
+    }
+
+    assert_eq!(next_queued(&database).await?, None);
+    assert_eq!(
+        Submission::count_queued_submissions(&database, false, &targets()).await?,
+        0
+    );
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn an_attempt_in_flight_leaves_the_queue(database: PgPool) -> anyhow::Result<()> {
+    let submission_id = add_approved_submission(&database).await?;
+    Post::add_post(
+        &database,
+        AddPost {
+            submission_id,
+            target: String::from("mastodon"),
+            repost: false,
+        },
+    )
+    .await?;
+
+    assert_eq!(next_queued(&database).await?, None);
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn every_image_needs_alt_text_when_required(database: PgPool) -> anyhow::Result<()> {
+    let submission_id = add_approved_submission(&database).await?;
+    Image::add_images(
+        &database,
+        AddImages {
+            submission_id,
+            image_paths: vec![
+                String::from("https://example.com/art-1.png"),
+                String::from("https://example.com/art-2.png"),
+            ],
+            alt_texts: vec![Some(String::from("Alt text"))],
+        },
+    )
+    .await?;
+
+    assert_eq!(
+        Submission::count_queued_submissions(&database, true, &targets()).await?,
+        0
+    );
+    assert_eq!(
+        Submission::count_queued_submissions(&database, false, &targets()).await?,
+        1
+    );
+
+    Ok(())
+}